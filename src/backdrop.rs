@@ -1,9 +1,15 @@
 use std::time::Duration;
 
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
 use rand::Rng;
 
-use crate::{AppState, Layers, MyAssets, ORIGINAL_TARGET_FPS};
+use crate::{
+    collision::{sprite_size, Collider},
+    playfield::PLAYFIELD,
+    rng::GameRng,
+    simulation::{Interpolated, TickSet},
+    AppState, Layers, MyAssets,
+};
 
 const MIN_STAR_SPAWN_SECONDS: f32 = 1.0;
 const MAX_STAR_SPAWN_SECONDS: f32 = 2.4;
@@ -20,6 +26,23 @@ impl Plugin for BackdropPlugin {
                 (setup_initial_backdrop, setup_backdrop_spawning)
                     .in_schedule(OnExit(AppState::Loading)),
             )
+            .add_systems(
+                (
+                    clear_backdrop,
+                    setup_initial_backdrop,
+                    setup_backdrop_spawning,
+                )
+                    .in_schedule(OnExit(AppState::GameOver)),
+            )
+            .add_systems(
+                (
+                    clear_backdrop,
+                    setup_initial_backdrop,
+                    setup_backdrop_spawning,
+                )
+                    .in_schedule(OnExit(AppState::GameWon)),
+            )
+            // Also scrolls behind the menu.
             .add_systems(
                 (
                     spawn_stars,
@@ -27,11 +50,18 @@ impl Plugin for BackdropPlugin {
                     obstacle_movement,
                     despawn_obstacle,
                 )
-                    .in_set(OnUpdate(AppState::InGame)),
+                    .distributive_run_if(scrolling)
+                    .before(TickSet::Interpolation)
+                    .in_schedule(CoreSchedule::FixedUpdate),
             );
     }
 }
 
+/// Scrolls while playing and in the menu, but stands still while paused or after the game.
+fn scrolling(state: Res<State<AppState>>) -> bool {
+    matches!(state.0, AppState::InGame | AppState::MainMenu)
+}
+
 #[derive(Resource)]
 struct BackdropSpawnConfig {
     star_timer: Timer,
@@ -55,47 +85,47 @@ impl Obstacle {
 
 fn setup_initial_backdrop(
     mut commands: Commands,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
     assets: Res<Assets<Image>>,
     my_assets: Res<MyAssets>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let window = primary_query.single();
-
     for _ in 0..30 {
-        let next_star_type = rand::thread_rng().gen_range(0..3);
+        let next_star_type = rng.backdrop.gen_range(0..3);
         let img_handle = match next_star_type {
             0 => my_assets.star.clone(),
             1 => my_assets.star1.clone(),
             _ => my_assets.star2.clone(),
         };
 
-        let img_size = assets.get(&img_handle).unwrap().size();
+        let collider = Collider::new(sprite_size(&assets, &img_handle));
+        let img_size = collider.size;
 
-        let min_x_offset = -(window.width() / 2.0) + (img_size.x / 2.);
-        let max_x_offset = window.width() / 2.0 - (img_size.x / 2.);
+        let min_x_offset = -(PLAYFIELD.x / 2.0) + (img_size.x / 2.);
+        let max_x_offset = PLAYFIELD.x / 2.0 - (img_size.x / 2.);
 
-        let min_y_offset = -(window.height() / 2.0) + (img_size.y / 2.);
-        let max_y_offset = window.height() / 2.0 - (img_size.y / 2.);
+        let min_y_offset = -(PLAYFIELD.y / 2.0) + (img_size.y / 2.);
+        let max_y_offset = PLAYFIELD.y / 2.0 - (img_size.y / 2.);
 
         // TODO: Technicaly they have a slightly different speed here, but it's close enough for now.
         let star = Obstacle::star();
         let mut sprite = SpriteBundle {
             texture: img_handle.clone(),
             transform: Transform::from_xyz(
-                rand::thread_rng().gen_range(min_x_offset..max_x_offset),
-                rand::thread_rng().gen_range(min_y_offset..max_y_offset),
+                rng.backdrop.gen_range(min_x_offset..max_x_offset),
+                rng.backdrop.gen_range(min_y_offset..max_y_offset),
                 Layers::Stars.order_nr(),
             ),
             ..default()
         };
 
         sprite.sprite.color.set_a(0.05);
-        commands.spawn((sprite, star));
+        let interpolated = Interpolated::new(sprite.transform.translation);
+        commands.spawn((sprite, star, collider, interpolated));
     }
 
     let planet = Obstacle::planet();
 
-    let next_planet_type = rand::thread_rng().gen_range(0..10);
+    let next_planet_type = rng.backdrop.gen_range(0..10);
     let img_handle = match next_planet_type {
         0 => my_assets.planet00.clone(),
         1 => my_assets.planet01.clone(),
@@ -109,121 +139,130 @@ fn setup_initial_backdrop(
         _ => my_assets.planet09.clone(),
     };
 
-    let img_size = assets.get(&img_handle).unwrap().size();
+    let collider = Collider::new(sprite_size(&assets, &img_handle));
+    let img_size = collider.size;
+
+    let min_x_offset = -(PLAYFIELD.x / 2.0);
+    let max_x_offset = PLAYFIELD.x / 2.0;
 
-    let min_x_offset = -(window.width() / 2.0);
-    let max_x_offset = window.width() / 2.0;
+    let translation = Vec3::new(
+        rng.backdrop.gen_range(min_x_offset..max_x_offset),
+        (PLAYFIELD.y / 2.) - (img_size.y / 2.),
+        Layers::Planets.order_nr(),
+    );
 
     commands.spawn((
         SpriteBundle {
             texture: img_handle,
-            transform: Transform::from_xyz(
-                rand::thread_rng().gen_range(min_x_offset..max_x_offset),
-                (window.height() / 2.) - (img_size.y / 2.),
-                Layers::Planets.order_nr(),
-            ),
+            transform: Transform::from_translation(translation),
             ..default()
         },
         planet,
+        collider,
+        Interpolated::new(translation),
     ));
 }
 
-fn setup_backdrop_spawning(mut commands: Commands) {
+fn clear_backdrop(mut commands: Commands, obstacle_query: Query<Entity, With<Obstacle>>) {
+    for obs_entity in &obstacle_query {
+        commands.entity(obs_entity).despawn();
+    }
+}
+
+fn setup_backdrop_spawning(mut commands: Commands, mut rng: ResMut<GameRng>) {
     commands.insert_resource(BackdropSpawnConfig {
         star_timer: Timer::new(
-            Duration::from_secs_f32(random_star_spawn_time()),
+            Duration::from_secs_f32(random_star_spawn_time(&mut rng.backdrop)),
             TimerMode::Once,
         ),
         planet_timer: Timer::new(
-            Duration::from_secs_f32(random_planet_spawn_time()),
+            Duration::from_secs_f32(random_planet_spawn_time(&mut rng.backdrop)),
             TimerMode::Once,
         ),
     })
 }
 
-fn random_star_spawn_time() -> f32 {
-    rand::thread_rng().gen_range(MIN_STAR_SPAWN_SECONDS..=MAX_STAR_SPAWN_SECONDS)
+fn random_star_spawn_time(rng: &mut impl Rng) -> f32 {
+    rng.gen_range(MIN_STAR_SPAWN_SECONDS..=MAX_STAR_SPAWN_SECONDS)
 }
 
-fn random_planet_spawn_time() -> f32 {
-    rand::thread_rng().gen_range(MIN_PLANET_SPAWN_SECONDS..=MAX_PLANET_SPAWN_SECONDS)
+fn random_planet_spawn_time(rng: &mut impl Rng) -> f32 {
+    rng.gen_range(MIN_PLANET_SPAWN_SECONDS..=MAX_PLANET_SPAWN_SECONDS)
 }
 
-fn obstacle_movement(time: Res<Time>, mut sprite_position: Query<(&mut Obstacle, &mut Transform)>) {
+fn obstacle_movement(mut sprite_position: Query<(&mut Obstacle, &mut Transform)>) {
     for (obs, mut transform) in &mut sprite_position {
-        transform.translation.y -= obs.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
+        transform.translation.y -= obs.speed;
     }
 }
 
 fn spawn_stars(
     mut commands: Commands,
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut config: ResMut<BackdropSpawnConfig>,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
     assets: Res<Assets<Image>>,
     my_assets: Res<MyAssets>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let window = primary_query.single();
-
-    config.star_timer.tick(time.delta());
+    config.star_timer.tick(fixed_time.period);
 
     if config.star_timer.finished() {
         config.star_timer = Timer::new(
-            Duration::from_secs_f32(random_star_spawn_time()),
+            Duration::from_secs_f32(random_star_spawn_time(&mut rng.backdrop)),
             TimerMode::Once,
         );
 
         for _ in 0..3 {
             let star = Obstacle::star();
-            let next_star_type = rand::thread_rng().gen_range(0..3);
+            let next_star_type = rng.backdrop.gen_range(0..3);
             let img_handle = match next_star_type {
                 0 => my_assets.star.clone(),
                 1 => my_assets.star1.clone(),
                 _ => my_assets.star2.clone(),
             };
 
-            let img_size = assets.get(&img_handle).unwrap().size();
+            let collider = Collider::new(sprite_size(&assets, &img_handle));
+            let img_size = collider.size;
 
-            let min_x_offset = -(window.width() / 2.0) + (img_size.x / 2.);
-            let max_x_offset = window.width() / 2.0 - (img_size.x / 2.);
+            let min_x_offset = -(PLAYFIELD.x / 2.0) + (img_size.x / 2.);
+            let max_x_offset = PLAYFIELD.x / 2.0 - (img_size.x / 2.);
 
             let mut sprite = SpriteBundle {
                 texture: img_handle,
                 transform: Transform::from_xyz(
-                    rand::thread_rng().gen_range(min_x_offset..max_x_offset),
-                    (window.height() / 2.) + (img_size.y / 2.),
+                    rng.backdrop.gen_range(min_x_offset..max_x_offset),
+                    (PLAYFIELD.y / 2.) + (img_size.y / 2.),
                     Layers::Stars.order_nr(),
                 ),
                 ..default()
             };
             sprite.sprite.color.set_a(0.05);
 
-            commands.spawn((sprite, star));
+            let interpolated = Interpolated::new(sprite.transform.translation);
+            commands.spawn((sprite, star, collider, interpolated));
         }
     }
 }
 
 fn spawn_planets(
     mut commands: Commands,
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut config: ResMut<BackdropSpawnConfig>,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
     assets: Res<Assets<Image>>,
     my_assets: Res<MyAssets>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let window = primary_query.single();
-
-    config.planet_timer.tick(time.delta());
+    config.planet_timer.tick(fixed_time.period);
 
     if config.planet_timer.finished() {
         config.planet_timer = Timer::new(
-            Duration::from_secs_f32(random_planet_spawn_time()),
+            Duration::from_secs_f32(random_planet_spawn_time(&mut rng.backdrop)),
             TimerMode::Once,
         );
 
         let planet = Obstacle::planet();
 
-        let next_planet_type = rand::thread_rng().gen_range(0..10);
+        let next_planet_type = rng.backdrop.gen_range(0..10);
         let img_handle = match next_planet_type {
             0 => my_assets.planet00.clone(),
             1 => my_assets.planet01.clone(),
@@ -237,44 +276,43 @@ fn spawn_planets(
             _ => my_assets.planet09.clone(),
         };
 
-        let img_size = assets.get(&img_handle).unwrap().size();
+        let collider = Collider::new(sprite_size(&assets, &img_handle));
+        let img_size = collider.size;
 
-        let min_x_offset = -(window.width() / 2.0);
-        let max_x_offset = window.width() / 2.0;
+        let min_x_offset = -(PLAYFIELD.x / 2.0);
+        let max_x_offset = PLAYFIELD.x / 2.0;
+
+        let translation = Vec3::new(
+            rng.backdrop.gen_range(min_x_offset..max_x_offset),
+            (PLAYFIELD.y / 2.) + (img_size.y / 2.),
+            Layers::Planets.order_nr(),
+        );
 
         commands.spawn((
             SpriteBundle {
                 texture: img_handle,
-                transform: Transform::from_xyz(
-                    rand::thread_rng().gen_range(min_x_offset..max_x_offset),
-                    (window.height() / 2.) + (img_size.y / 2.),
-                    Layers::Planets.order_nr(),
-                ),
+                transform: Transform::from_translation(translation),
                 ..default()
             },
             planet,
+            collider,
+            Interpolated::new(translation),
         ));
     }
 }
 
 fn despawn_obstacle(
     mut commands: Commands,
-    mut sprite_position: Query<(Entity, &mut Obstacle, &mut Transform, &Handle<Image>)>,
-    assets: Res<Assets<Image>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut sprite_position: Query<(Entity, &mut Obstacle, &mut Transform, &Collider)>,
 ) {
-    let window = window_query.single();
-
-    for (obs_entity, _obs, transform, img_handle) in &mut sprite_position {
-        let obs_size = assets.get(img_handle).unwrap().size();
-
-        if obstacle_past_bottom(transform.translation.y, window, obs_size) {
+    for (obs_entity, _obs, transform, collider) in &mut sprite_position {
+        if obstacle_past_bottom(transform.translation.y, collider.size) {
             commands.entity(obs_entity).despawn();
         }
     }
 }
 
-fn obstacle_past_bottom(y: f32, window: &Window, obj_size: Vec2) -> bool {
-    let min_y = -(window.height() / 2.) - (obj_size.y / 2.);
+fn obstacle_past_bottom(y: f32, obj_size: Vec2) -> bool {
+    let min_y = -(PLAYFIELD.y / 2.) - (obj_size.y / 2.);
     y < min_y
 }
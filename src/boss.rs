@@ -0,0 +1,373 @@
+//! Bosses end the levels of the wave script. A boss flies in once the players reached the next level,
+//! only takes damage in its hit zones and changes weapon and movement as its health runs low.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    collision::{Collider, CollisionGridSystem, CollisionMasks},
+    enemy_spawning::EnemyArchetypes,
+    playfield::PLAYFIELD,
+    shooting::WeaponCatalog,
+    simulation::{Interpolated, TickSet},
+    AppState, Enemy, Layers, MyAssets,
+};
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BossDefeated>()
+            .add_systems(
+                (
+                    boss_movement.before(CollisionGridSystem),
+                    boss_phases,
+                    check_boss_defeated,
+                )
+                    .in_set(TickSet::Gameplay)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(end_boss_fight.in_schedule(OnExit(AppState::GameOver)))
+            .add_system(end_boss_fight.in_schedule(OnExit(AppState::GameWon)));
+    }
+}
+
+/// A boss from `assets/data/fleet.enemies.ron`.
+#[derive(Deserialize)]
+pub struct BossArchetype {
+    /// Shown above its health bar.
+    pub name: String,
+    pub sprite: String,
+    #[serde(skip)]
+    pub image: Handle<Image>,
+    /// Bosses are drawn larger than their sprite, collisions included.
+    pub scale: f32,
+    pub health: u32,
+    pub collision_damage: u32,
+    pub bounty: u32,
+    /// Pixels per tick while flying in.
+    pub speed: f32,
+    /// Where it stops flying in, in pixels above the center of the playfield.
+    pub hover_height: f32,
+    pub hit_zones: Vec<HitZone>,
+    /// The first phase starts right away, the others once the health drops to their threshold.
+    pub phases: Vec<BossPhase>,
+}
+
+/// Part of the boss that takes damage, shots anywhere else bounce off its armor.
+#[derive(Deserialize, Clone, Debug)]
+pub struct HitZone {
+    /// Center of the zone in pixels, relative to the center of the boss.
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    pub damage_multiplier: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossPhase {
+    /// Fraction of the full health at which the phase starts.
+    pub health: f32,
+    /// Key of the weapon in the `WeaponCatalog`, unarmed without.
+    pub weapon: Option<String>,
+    pub movement: BossMovement,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum BossMovement {
+    Hover,
+    /// Flies from side to side at this many pixels per tick.
+    Sweep(f32),
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub name: String,
+    pub full_health: u32,
+    pub phase: usize,
+    phases: Vec<BossPhase>,
+    speed: f32,
+    hover_height: f32,
+    /// 1 while sweeping to the right, -1 to the left.
+    direction: f32,
+}
+
+impl Boss {
+    fn movement(&self) -> BossMovement {
+        self.phases
+            .get(self.phase)
+            .map_or(BossMovement::Hover, |phase| phase.movement)
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct HitZones(pub Vec<HitZone>);
+
+impl HitZones {
+    /// Damage of a shot hitting at `offset` from the center of the boss.
+    pub fn damage(&self, damage: u32, offset: Vec2) -> u32 {
+        self.0
+            .iter()
+            .find(|zone| {
+                let center = Vec2::new(zone.offset.0, zone.offset.1);
+                let half_size = Vec2::new(zone.size.0, zone.size.1) / 2.0;
+                (offset - center).abs().cmple(half_size).all()
+            })
+            .map_or(0, |zone| {
+                (damage as f32 * zone.damage_multiplier).round() as u32
+            })
+    }
+}
+
+/// The boss being fought, there is only one at a time.
+#[derive(Resource)]
+pub struct BossFight {
+    pub boss: Entity,
+    pub level: u32,
+}
+
+/// Sent once the boss ending `level` is destroyed, the wave script goes on with the next level.
+pub struct BossDefeated {
+    pub level: u32,
+}
+
+/// Starts the fight with the boss `key`, it flies in from the top.
+pub fn spawn_boss(
+    commands: &mut Commands,
+    key: &str,
+    level: u32,
+    enemies: &EnemyArchetypes,
+    weapons: &WeaponCatalog,
+    images: &Assets<Image>,
+    masks: &CollisionMasks,
+) {
+    let archetype = enemies.boss(key);
+    let collider = Collider::from_image(images, masks, &archetype.image).scaled(archetype.scale);
+    let translation = Vec3::new(
+        0.0,
+        PLAYFIELD.y / 2.0 + collider.size.y / 2.0,
+        Layers::Actors.order_nr(),
+    );
+
+    let boss = commands
+        .spawn((
+            SpriteBundle {
+                texture: archetype.image.clone(),
+                sprite: Sprite {
+                    custom_size: Some(collider.size),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            Enemy {
+                health: archetype.health,
+                collision_damage: archetype.collision_damage,
+                bounty: archetype.bounty,
+                speed: archetype.speed,
+                weapon: archetype.phases.first().and_then(|phase| {
+                    phase
+                        .weapon
+                        .as_ref()
+                        .map(|weapon| weapons.weapon(weapon, false))
+                }),
+            },
+            Boss {
+                name: archetype.name.clone(),
+                full_health: archetype.health,
+                phase: 0,
+                phases: archetype.phases.clone(),
+                speed: archetype.speed,
+                hover_height: archetype.hover_height,
+                direction: 1.0,
+            },
+            HitZones(archetype.hit_zones.clone()),
+            collider,
+            Interpolated::new(translation),
+        ))
+        .id();
+
+    commands.insert_resource(BossFight { boss, level });
+}
+
+fn boss_movement(mut boss_query: Query<(&mut Boss, &mut Transform, &Collider)>) {
+    for (mut boss, mut transform, collider) in &mut boss_query {
+        // Also brings it back down after being pushed by a shot.
+        if transform.translation.y > boss.hover_height {
+            transform.translation.y = (transform.translation.y - boss.speed).max(boss.hover_height);
+            continue;
+        }
+
+        if let BossMovement::Sweep(speed) = boss.movement() {
+            let max_x = PLAYFIELD.x / 2.0 - collider.size.x / 2.0;
+            transform.translation.x += boss.direction * speed;
+            if transform.translation.x.abs() >= max_x {
+                transform.translation.x = transform.translation.x.clamp(-max_x, max_x);
+                boss.direction = -boss.direction;
+            }
+        }
+    }
+}
+
+fn boss_phases(
+    mut boss_query: Query<(&mut Boss, &mut Enemy)>,
+    my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+) {
+    let weapons = weapon_catalogs.get(&my_assets.weapons).unwrap();
+
+    for (mut boss, mut enemy) in &mut boss_query {
+        let health = enemy.health as f32 / boss.full_health as f32;
+        let Some(phase) = boss.phases.iter().rposition(|phase| health <= phase.health) else {
+            continue;
+        };
+        if phase <= boss.phase {
+            continue;
+        }
+
+        boss.phase = phase;
+        enemy.weapon = boss.phases[phase]
+            .weapon
+            .as_ref()
+            .map(|weapon| weapons.weapon(weapon, false));
+    }
+}
+
+/// Bosses don't escape, so once it's gone it was destroyed.
+pub fn check_boss_defeated(
+    mut commands: Commands,
+    fight: Option<Res<BossFight>>,
+    boss_query: Query<(), With<Boss>>,
+    mut ev_bossdefeated: EventWriter<BossDefeated>,
+) {
+    let Some(fight) = fight else {
+        return;
+    };
+    if boss_query.contains(fight.boss) {
+        return;
+    }
+
+    ev_bossdefeated.send(BossDefeated { level: fight.level });
+    commands.remove_resource::<BossFight>();
+}
+
+fn end_boss_fight(mut commands: Commands) {
+    commands.remove_resource::<BossFight>();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, prelude::*};
+
+    use super::{Boss, BossDefeated, BossFight, HitZone, HitZones};
+    use crate::{
+        enemy_spawning::{WaveDirector, WaveScript},
+        test_harness::TestGame,
+        Enemy,
+    };
+
+    fn level_with_boss() -> WaveScript {
+        ron::from_str(
+            r#"#![enable(implicit_some)]
+            (
+                levels: [
+                    (waves: [(entries: [(archetype: "trespasser", count: 1)])], boss: "dread_lord"),
+                    (waves: [(entries: [(archetype: "trespasser", count: 1)])]),
+                ],
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn despawn_enemies(game: &mut TestGame) {
+        let enemies: Vec<Entity> = game
+            .app
+            .world
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(&game.app.world)
+            .collect();
+        for enemy in enemies {
+            game.app.world.despawn(enemy);
+        }
+    }
+
+    /// Levels the player up and clears the first wave, so the boss flies in.
+    fn start_boss_fight(game: &mut TestGame) -> Entity {
+        game.player_state_mut().level = 2;
+        game.step();
+        despawn_enemies(game);
+        game.step_frames(2);
+        game.app.world.resource::<BossFight>().boss
+    }
+
+    #[test]
+    fn shots_only_hurt_in_hit_zones() {
+        let zones = HitZones(vec![
+            HitZone {
+                offset: (0.0, -20.0),
+                size: (40.0, 40.0),
+                damage_multiplier: 2.0,
+            },
+            HitZone {
+                offset: (60.0, 0.0),
+                size: (40.0, 80.0),
+                damage_multiplier: 0.5,
+            },
+        ]);
+
+        assert_eq!(zones.damage(10, Vec2::new(5.0, -30.0)), 20);
+        assert_eq!(zones.damage(10, Vec2::new(70.0, 30.0)), 5);
+        assert_eq!(zones.damage(10, Vec2::new(0.0, 60.0)), 0);
+    }
+
+    #[test]
+    fn boss_flies_in_once_the_player_reaches_the_next_level() {
+        let mut game = TestGame::with_waves(level_with_boss());
+        despawn_enemies(&mut game);
+        game.step_frames(2);
+
+        assert!(game.app.world.get_resource::<BossFight>().is_none());
+
+        let boss = start_boss_fight(&mut game);
+        let start = game.position(boss);
+        game.step_frames(40);
+
+        assert!(game.position(boss).y < start.y);
+        assert_eq!(game.enemy_count(), 1);
+    }
+
+    #[test]
+    fn boss_switches_weapons_when_its_health_runs_low() {
+        let mut game = TestGame::with_waves(level_with_boss());
+        let boss = start_boss_fight(&mut game);
+        let first_weapon = game.app.world.get::<Enemy>(boss).unwrap().weapon.clone();
+
+        let full_health = game.app.world.get::<Boss>(boss).unwrap().full_health;
+        game.app.world.get_mut::<Enemy>(boss).unwrap().health = full_health / 2;
+        game.step();
+
+        assert_eq!(game.app.world.get::<Boss>(boss).unwrap().phase, 1);
+        let weapon = game.app.world.get::<Enemy>(boss).unwrap().weapon.clone();
+        assert_ne!(
+            weapon.map(|weapon| weapon.key),
+            first_weapon.map(|weapon| weapon.key)
+        );
+    }
+
+    #[test]
+    fn defeating_the_boss_starts_the_next_level() {
+        let mut game = TestGame::with_waves(level_with_boss());
+        let boss = start_boss_fight(&mut game);
+        game.step_frames(40);
+        assert_eq!(game.app.world.resource::<WaveDirector>().level, 1);
+
+        game.app.world.despawn(boss);
+        game.step();
+
+        let events = game.app.world.resource::<Events<BossDefeated>>();
+        let mut reader = events.get_reader();
+        let defeated: Vec<_> = reader.iter(events).collect();
+        assert_eq!(defeated.len(), 1);
+        assert_eq!(defeated[0].level, 1);
+        assert_eq!(game.app.world.resource::<WaveDirector>().level, 2);
+    }
+}
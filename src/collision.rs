@@ -0,0 +1,479 @@
+//! Sizes and shapes for collisions and bounds, with the masks built once per sprite after loading,
+//! and a grid of the enemies so that collisions are only checked against the ones nearby.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bevy::{asset::HandleId, prelude::*, render::render_resource::TextureFormat};
+
+use crate::{
+    enemy_spawning::EnemyArchetypes, shooting::WeaponCatalog, simulation::TickSet, AppState, Enemy,
+    MyAssets,
+};
+
+/// Edge length of a grid cell, about the size of the bigger ships.
+const CELL_SIZE: f32 = 128.0;
+/// Pixels at least this opaque can be hit.
+const SOLID_ALPHA: u8 = 128;
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollisionGrid>()
+            .init_resource::<CollisionMasks>()
+            .add_system(build_collision_masks.in_schedule(OnExit(AppState::Loading)))
+            .add_system(build_collision_masks.in_schedule(OnExit(AppState::LoadingDependencies)))
+            .add_system(
+                update_collision_grid
+                    .in_set(TickSet::Gameplay)
+                    .in_set(CollisionGridSystem)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// Rebuilds the `CollisionGrid`, systems moving enemies have to run before and collision checks after.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CollisionGridSystem;
+
+/// Size and shape of an entity, taken from its sprite when it's spawned.
+#[derive(Component, Clone, PartialEq, Debug)]
+pub struct Collider {
+    /// Size of the sprite, used to tell whether it's still on the playfield.
+    pub size: Vec2,
+    /// Area that can be hit, centered on the sprite. Can be smaller than the sprite.
+    pub hitbox: Vec2,
+    /// Which pixels of the sprite can be hit, all of them without a mask.
+    pub mask: Option<Arc<CollisionMask>>,
+}
+
+impl Collider {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            size,
+            hitbox: size,
+            mask: None,
+        }
+    }
+
+    /// Sprites are loaded before the game starts, so they are always there when spawning.
+    pub fn from_image(
+        images: &Assets<Image>,
+        masks: &CollisionMasks,
+        image: &Handle<Image>,
+    ) -> Self {
+        Self {
+            mask: masks.mask(images, image, false),
+            ..Self::new(sprite_size(images, image))
+        }
+    }
+
+    /// For sprites drawn upside down.
+    pub fn from_flipped_image(
+        images: &Assets<Image>,
+        masks: &CollisionMasks,
+        image: &Handle<Image>,
+    ) -> Self {
+        Self {
+            mask: masks.mask(images, image, true),
+            ..Self::new(sprite_size(images, image))
+        }
+    }
+
+    pub fn with_hitbox(self, hitbox: Vec2) -> Self {
+        Self { hitbox, ..self }
+    }
+
+    /// For sprites drawn larger than their image, the mask stays at the size of the image.
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            size: self.size * factor,
+            hitbox: self.hitbox * factor,
+            ..self
+        }
+    }
+
+    /// Hitboxes have to overlap and, where they do, both sprites need a solid pixel at the same spot.
+    pub fn collides(&self, position: Vec3, other: &Collider, other_position: Vec3) -> bool {
+        let position = position.truncate();
+        let other_position = other_position.truncate();
+        let overlap = bounds(position, self.hitbox).intersect(bounds(other_position, other.hitbox));
+        if overlap.is_empty() {
+            return false;
+        }
+        if self.mask.is_none() && other.mask.is_none() {
+            return true;
+        }
+
+        // Steps by world units, scaled sprites map them to their pixels in `solid_at`.
+        let size = overlap.size().ceil();
+        (0..size.y as u32).any(|y| {
+            (0..size.x as u32).any(|x| {
+                let point = overlap.min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                self.solid_at(point - position) && other.solid_at(point - other_position)
+            })
+        })
+    }
+
+    fn solid_at(&self, offset: Vec2) -> bool {
+        match &self.mask {
+            Some(mask) => mask.solid_at((offset / self.size + 0.5) * mask.size()),
+            None => true,
+        }
+    }
+}
+
+/// Size of a sprite that is drawn at the size of its image.
+pub fn sprite_size(images: &Assets<Image>, image: &Handle<Image>) -> Vec2 {
+    images
+        .get(image)
+        .expect("Sprites have to be loaded before spawning anything using them.")
+        .size()
+}
+
+/// Unlike `Rect::from_center_size` this allows negative sizes, they just never overlap anything.
+fn bounds(center: Vec2, size: Vec2) -> Rect {
+    Rect {
+        min: center - size / 2.0,
+        max: center + size / 2.0,
+    }
+}
+
+/// The opaque pixels of a sprite.
+#[derive(PartialEq, Debug)]
+pub struct CollisionMask {
+    width: usize,
+    height: usize,
+    solid: Vec<bool>,
+}
+
+impl CollisionMask {
+    /// Only sprites with 8 bit RGBA pixels have an alpha channel to take the mask from.
+    pub fn from_image(image: &Image) -> Option<Self> {
+        let format = image.texture_descriptor.format;
+        if format != TextureFormat::Rgba8UnormSrgb && format != TextureFormat::Rgba8Unorm {
+            return None;
+        }
+
+        let size = image.texture_descriptor.size;
+        Some(Self {
+            width: size.width as usize,
+            height: size.height as usize,
+            solid: image
+                .data
+                .chunks_exact(4)
+                .map(|pixel| pixel[3] >= SOLID_ALPHA)
+                .collect(),
+        })
+    }
+
+    fn flip_y(&self) -> Self {
+        Self {
+            solid: self
+                .solid
+                .chunks_exact(self.width)
+                .rev()
+                .flatten()
+                .copied()
+                .collect(),
+            ..*self
+        }
+    }
+
+    /// Takes the point from the bottom left corner of the sprite, the pixels start at the top.
+    fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    fn solid_at(&self, point: Vec2) -> bool {
+        if point.x < 0.0 || point.y < 0.0 {
+            return false;
+        }
+        let (x, y) = (point.x as usize, point.y as usize);
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        self.solid[(self.height - 1 - y) * self.width + x]
+    }
+}
+
+/// The masks of every sprite that collides, upright and flipped, so they are built once instead of with every spawn.
+#[derive(Resource, Default)]
+pub struct CollisionMasks {
+    masks: HashMap<HandleId, [Option<Arc<CollisionMask>>; 2]>,
+}
+
+impl CollisionMasks {
+    /// Sprites that aren't loaded yet are left out.
+    pub fn insert(&mut self, images: &Assets<Image>, image: &Handle<Image>) {
+        if self.masks.contains_key(&image.id()) {
+            return;
+        }
+        let Some(loaded) = images.get(image) else {
+            return;
+        };
+        let mask = CollisionMask::from_image(loaded);
+        let flipped = mask.as_ref().map(|mask| Arc::new(mask.flip_y()));
+        self.masks.insert(image.id(), [mask.map(Arc::new), flipped]);
+    }
+
+    /// Sprites only showing up with a hot-reloaded data file aren't built yet, they get their mask right away.
+    fn mask(
+        &self,
+        images: &Assets<Image>,
+        image: &Handle<Image>,
+        flipped: bool,
+    ) -> Option<Arc<CollisionMask>> {
+        match self.masks.get(&image.id()) {
+            Some(masks) => masks[flipped as usize].clone(),
+            None => {
+                let mask = CollisionMask::from_image(images.get(image)?)?;
+                Some(Arc::new(if flipped { mask.flip_y() } else { mask }))
+            }
+        }
+    }
+}
+
+/// Runs once the sprites are loaded, the ones referenced by data files come after `MyAssets`.
+pub fn build_collision_masks(
+    mut masks: ResMut<CollisionMasks>,
+    images: Res<Assets<Image>>,
+    my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+    enemy_archetypes: Res<Assets<EnemyArchetypes>>,
+) {
+    let weapons = weapon_catalogs.get(&my_assets.weapons);
+    let enemies = enemy_archetypes.get(&my_assets.enemies);
+    let sprites = std::iter::once(&my_assets.player)
+        .chain(
+            weapons
+                .into_iter()
+                .flat_map(|weapons| weapons.projectile_images()),
+        )
+        .chain(enemies.into_iter().flat_map(|enemies| enemies.images()));
+
+    for sprite in sprites {
+        masks.insert(&images, sprite);
+    }
+}
+
+/// The enemies by the grid cells their hitboxes overlap, rebuilt every tick.
+/// Only gives candidates, whether they really collide is still up to `Collider::collides`.
+#[derive(Resource, Default)]
+pub struct CollisionGrid {
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl CollisionGrid {
+    pub fn clear(&mut self) {
+        // Keeps the cells, so that their memory gets reused next tick.
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+
+    /// Entities moved during a tick need to be inserted again to be found at their new position.
+    pub fn insert(&mut self, entity: Entity, position: Vec3, collider: &Collider) {
+        for cell in cells(position, collider) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+    }
+
+    /// Entities that might collide with the collider at the position, in the order they were inserted.
+    /// Sorting by entity would not do, both games of a netplay session have their own entity ids.
+    pub fn candidates(&self, position: Vec3, collider: &Collider) -> Vec<Entity> {
+        let mut candidates = Vec::new();
+        for entity in cells(position, collider)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+        {
+            if !candidates.contains(entity) {
+                candidates.push(*entity);
+            }
+        }
+        candidates
+    }
+}
+
+fn cells(position: Vec3, collider: &Collider) -> impl Iterator<Item = (i32, i32)> {
+    let half = collider.hitbox / 2.0;
+    let min = ((position.truncate() - half) / CELL_SIZE).floor();
+    let max = ((position.truncate() + half) / CELL_SIZE).floor();
+    (min.x as i32..=max.x as i32)
+        .flat_map(move |x| (min.y as i32..=max.y as i32).map(move |y| (x, y)))
+}
+
+fn update_collision_grid(
+    mut grid: ResMut<CollisionGrid>,
+    enemies_query: Query<(Entity, &Transform, &Collider), With<Enemy>>,
+) {
+    grid.clear();
+    for (entity, transform, collider) in &enemies_query {
+        grid.insert(entity, transform.translation, collider);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use bevy::prelude::*;
+
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+    use super::{Collider, CollisionGrid, CollisionMask};
+    use crate::test_harness::TestGame;
+
+    fn diamond_ship(size: u32) -> Collider {
+        Collider {
+            mask: CollisionMask::from_image(&diamond(size)).map(Arc::new),
+            ..Collider::new(Vec2::splat(size as f32))
+        }
+    }
+
+    /// A diamond, so that the corners of the sprite are empty.
+    fn diamond(size: u32) -> Image {
+        let half = size as i32 / 2;
+        let data = (0..size as i32)
+            .flat_map(|y| (0..size as i32).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let inside = (x - half).abs() + (y - half).abs() < half;
+                [255, 255, 255, if inside { 255 } else { 0 }]
+            })
+            .collect();
+        Image::new(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    #[test]
+    fn ships_share_the_mask_of_their_sprite() {
+        let mut game = TestGame::new();
+        let first = game.spawn_enemy("trespasser", Vec2::new(-200.0, 200.0));
+        let second = game.spawn_enemy("trespasser", Vec2::new(200.0, 200.0));
+
+        let mask = |ship| game.app.world.get::<Collider>(ship).unwrap().mask.clone();
+        let (first, second) = (mask(first).unwrap(), mask(second).unwrap());
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn smaller_hitboxes_need_a_closer_hit() {
+        let ship = Collider::new(Vec2::new(100.0, 80.0));
+        let projectile = Collider::new(Vec2::new(10.0, 20.0));
+        let beside = Vec3::new(45.0, 0.0, 0.0);
+
+        assert!(ship.collides(Vec3::ZERO, &projectile, beside));
+        assert!(!ship
+            .with_hitbox(Vec2::new(60.0, 80.0))
+            .collides(Vec3::ZERO, &projectile, beside));
+    }
+
+    #[test]
+    fn shots_miss_the_empty_corners_of_a_sprite() {
+        let ship = diamond_ship(100);
+        let projectile = Collider::new(Vec2::new(10.0, 20.0));
+
+        assert!(ship.collides(Vec3::ZERO, &projectile, Vec3::new(0.0, 40.0, 0.0)));
+        assert!(ship.collides(Vec3::ZERO, &projectile, Vec3::new(-40.0, 0.0, 0.0)));
+        assert!(!ship.collides(Vec3::ZERO, &projectile, Vec3::new(45.0, 45.0, 0.0)));
+        assert!(!ship.collides(Vec3::ZERO, &projectile, Vec3::new(-45.0, -40.0, 0.0)));
+    }
+
+    #[test]
+    fn masks_only_collide_where_both_are_solid() {
+        let ship = diamond_ship(100);
+
+        // The bounding boxes overlap a lot, the diamonds only touch at the tips.
+        assert!(!ship.collides(Vec3::ZERO, &ship, Vec3::new(60.0, 60.0, 0.0)));
+        assert!(ship.collides(Vec3::ZERO, &ship, Vec3::new(60.0, 20.0, 0.0)));
+    }
+
+    #[test]
+    fn grid_only_finds_nearby_entities() {
+        let ship = Collider::new(Vec2::new(100.0, 80.0));
+        let projectile = Collider::new(Vec2::new(10.0, 20.0));
+        let mut grid = CollisionGrid::default();
+        grid.insert(Entity::from_raw(0), Vec3::new(120.0, 0.0, 0.0), &ship);
+        grid.insert(Entity::from_raw(1), Vec3::new(-400.0, 300.0, 0.0), &ship);
+
+        assert_eq!(
+            grid.candidates(Vec3::new(130.0, 10.0, 0.0), &projectile),
+            vec![Entity::from_raw(0)]
+        );
+        assert!(grid
+            .candidates(Vec3::new(400.0, -300.0, 0.0), &projectile)
+            .is_empty());
+    }
+
+    #[test]
+    fn grid_lists_entities_across_cells_once() {
+        let ship = Collider::new(Vec2::new(300.0, 300.0));
+        let mut grid = CollisionGrid::default();
+        grid.insert(Entity::from_raw(0), Vec3::ZERO, &ship);
+
+        assert_eq!(
+            grid.candidates(Vec3::ZERO, &ship),
+            vec![Entity::from_raw(0)]
+        );
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore]
+    fn bench_grid_against_checking_every_pair() {
+        let ship = Collider::new(Vec2::new(100.0, 80.0));
+        let projectile = Collider::new(Vec2::new(10.0, 20.0));
+        // Deterministic spread over the playfield, no need for a real rng here.
+        let spread = |i: u32, width: f32, height: f32| {
+            let x = (i.wrapping_mul(2_654_435_761) % 10_000) as f32 / 10_000.0;
+            let y = (i.wrapping_mul(40_503) % 10_000) as f32 / 10_000.0;
+            Vec3::new((x - 0.5) * width, (y - 0.5) * height, 0.0)
+        };
+        let enemies: Vec<Vec3> = (0..200).map(|i| spread(i, 1120.0, 605.0)).collect();
+        let projectiles: Vec<Vec3> = (0..5000).map(|i| spread(i + 7, 1120.0, 605.0)).collect();
+
+        let start = Instant::now();
+        let mut pairs_hits = 0;
+        for position in &projectiles {
+            for enemy in &enemies {
+                if ship.collides(*enemy, &projectile, *position) {
+                    pairs_hits += 1;
+                }
+            }
+        }
+        let pairs_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut grid = CollisionGrid::default();
+        for (i, enemy) in enemies.iter().enumerate() {
+            grid.insert(Entity::from_raw(i as u32), *enemy, &ship);
+        }
+        let mut grid_hits = 0;
+        for position in &projectiles {
+            for candidate in grid.candidates(*position, &projectile) {
+                let enemy = enemies[candidate.index() as usize];
+                if ship.collides(enemy, &projectile, *position) {
+                    grid_hits += 1;
+                }
+            }
+        }
+        let grid_time = start.elapsed();
+
+        println!(
+            "{} enemies, {} projectiles: every pair {:?}, grid {:?}",
+            enemies.len(),
+            projectiles.len(),
+            pairs_time,
+            grid_time
+        );
+        assert_eq!(pairs_hits, grid_hits);
+    }
+}
@@ -0,0 +1,500 @@
+//! Turns keyboard and gamepads into actions, so the game doesn't need to care what the player holds
+//! or how their keys are bound.
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        gamepad::{GamepadAxisType, GamepadButtonType},
+        InputSystem,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{coop::GameMode, storage};
+
+const BINDINGS_FILE: &str = "bindings";
+
+/// Stick movement below this is ignored, worn sticks rarely rest at exactly zero.
+pub const STICK_DEADZONE: f32 = 0.2;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .init_resource::<ActionState>()
+            .init_resource::<SecondPlayerActions>()
+            .add_system(
+                collect_actions
+                    .in_base_set(CoreSet::PreUpdate)
+                    .in_set(ActionSystem)
+                    .after(InputSystem),
+            );
+    }
+}
+
+/// Fills `ActionState` and `SecondPlayerActions`, systems overriding it (like replays) have to run after.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ActionSystem;
+
+/// Everything a player can do. Each one is a bit in `ActionState`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    Weapon5,
+    NextWeapon,
+    PreviousWeapon,
+    Pause,
+    Restart,
+    Up,
+    Down,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Weapon1,
+        Action::Weapon2,
+        Action::Weapon3,
+        Action::Weapon4,
+        Action::Weapon5,
+        Action::NextWeapon,
+        Action::PreviousWeapon,
+        Action::Pause,
+        Action::Restart,
+        Action::Up,
+        Action::Down,
+        Action::Confirm,
+        Action::Back,
+    ];
+
+    /// Actions whose keys can be changed in the settings, the menu keys stay the same.
+    pub const REBINDABLE: [Action; 13] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Weapon1,
+        Action::Weapon2,
+        Action::Weapon3,
+        Action::Weapon4,
+        Action::Weapon5,
+        Action::NextWeapon,
+        Action::PreviousWeapon,
+        Action::Pause,
+    ];
+
+    pub const WEAPONS: [Action; 5] = [
+        Action::Weapon1,
+        Action::Weapon2,
+        Action::Weapon3,
+        Action::Weapon4,
+        Action::Weapon5,
+    ];
+
+    /// Actions of the ship itself, which every player has on their own.
+    pub fn steers_ship(self) -> bool {
+        self < Action::Pause
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp => "MOVE UP",
+            Action::MoveDown => "MOVE DOWN",
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Fire => "FIRE",
+            Action::Weapon1 => "WEAPON 1",
+            Action::Weapon2 => "WEAPON 2",
+            Action::Weapon3 => "WEAPON 3",
+            Action::Weapon4 => "WEAPON 4",
+            Action::Weapon5 => "WEAPON 5",
+            Action::NextWeapon => "NEXT WEAPON",
+            Action::PreviousWeapon => "PREVIOUS WEAPON",
+            Action::Pause => "PAUSE",
+            Action::Restart => "RESTART",
+            Action::Up => "UP",
+            Action::Down => "DOWN",
+            Action::Confirm => "CONFIRM",
+            Action::Back => "BACK",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::MoveUp => &[KeyCode::W],
+            Action::MoveDown => &[KeyCode::S],
+            Action::MoveLeft => &[KeyCode::A],
+            Action::MoveRight => &[KeyCode::D],
+            Action::Fire => &[KeyCode::J],
+            Action::Weapon1 => &[KeyCode::Key1],
+            Action::Weapon2 => &[KeyCode::Key2],
+            Action::Weapon3 => &[KeyCode::Key3],
+            Action::Weapon4 => &[KeyCode::Key4],
+            Action::Weapon5 => &[KeyCode::Key5],
+            Action::NextWeapon => &[KeyCode::E],
+            Action::PreviousWeapon => &[KeyCode::Q],
+            Action::Pause => &[KeyCode::Escape],
+            Action::Restart => &[KeyCode::R],
+            Action::Up => &[KeyCode::Up],
+            Action::Down => &[KeyCode::Down],
+            Action::Confirm => &[KeyCode::Return],
+            Action::Back => &[KeyCode::Escape],
+        }
+    }
+
+    /// The second player uses the keys around the arrows and the numpad, they can't be rebound.
+    fn player_two_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::MoveUp => &[KeyCode::Up],
+            Action::MoveDown => &[KeyCode::Down],
+            Action::MoveLeft => &[KeyCode::Left],
+            Action::MoveRight => &[KeyCode::Right],
+            Action::Fire => &[KeyCode::RControl, KeyCode::Numpad0],
+            Action::Weapon1 => &[KeyCode::Numpad1],
+            Action::Weapon2 => &[KeyCode::Numpad2],
+            Action::Weapon3 => &[KeyCode::Numpad3],
+            Action::Weapon4 => &[KeyCode::Numpad4],
+            Action::Weapon5 => &[KeyCode::Numpad5],
+            Action::NextWeapon => &[KeyCode::PageDown],
+            Action::PreviousWeapon => &[KeyCode::Delete],
+            _ => &[],
+        }
+    }
+
+    fn buttons(self) -> &'static [GamepadButtonType] {
+        match self {
+            Action::MoveUp => &[GamepadButtonType::DPadUp],
+            Action::MoveDown => &[GamepadButtonType::DPadDown],
+            Action::MoveLeft => &[GamepadButtonType::DPadLeft],
+            Action::MoveRight => &[GamepadButtonType::DPadRight],
+            Action::Fire => &[GamepadButtonType::South, GamepadButtonType::RightTrigger2],
+            Action::NextWeapon => &[GamepadButtonType::RightTrigger],
+            Action::PreviousWeapon => &[GamepadButtonType::LeftTrigger],
+            Action::Pause | Action::Restart => &[GamepadButtonType::Start],
+            Action::Confirm => &[GamepadButtonType::South],
+            Action::Back => &[GamepadButtonType::East],
+            _ => &[],
+        }
+    }
+}
+
+/// Keys of every action, saved in `bindings.ron` (native) or the `localStorage` of the browser (wasm).
+/// Actions missing from the file keep their default keys.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::REBINDABLE
+                .into_iter()
+                .map(|action| (action, action.default_keys().to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn load() -> Self {
+        storage::load(BINDINGS_FILE)
+            .map(|ron| Self::from_ron(&ron))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(BINDINGS_FILE, &self.to_ron());
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys
+            .get(&action)
+            .map_or(action.default_keys(), Vec::as_slice)
+    }
+
+    /// Binds `key` to `action` only. An action that had the key gets the old keys of `action`
+    /// instead, so none ends up without. Keys of the second player and the menu are refused,
+    /// returns whether the key got bound.
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> bool {
+        let reserved = Action::ALL.into_iter().any(|fixed| {
+            fixed.player_two_keys().contains(&key)
+                || (!Action::REBINDABLE.contains(&fixed) && fixed.default_keys().contains(&key))
+        });
+        if reserved {
+            return false;
+        }
+
+        let previous = self.keys(action).to_vec();
+        for (other, keys) in &mut self.keys {
+            if *other == action || !keys.contains(&key) {
+                continue;
+            }
+            keys.retain(|bound| *bound != key);
+            if keys.is_empty() {
+                *keys = previous.clone();
+            }
+        }
+        self.keys.insert(action, vec![key]);
+        true
+    }
+
+    fn from_ron(ron: &str) -> Self {
+        match ron::from_str::<Self>(ron) {
+            Ok(loaded) => {
+                let mut bindings = Self::default();
+                bindings.keys.extend(loaded.keys);
+                bindings
+            }
+            Err(err) => {
+                warn!("Ignoring broken key bindings: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap()
+    }
+}
+
+/// Shorter names than `Debug`, for the menu.
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Key0 => "0".into(),
+        KeyCode::Key1 => "1".into(),
+        KeyCode::Key2 => "2".into(),
+        KeyCode::Key3 => "3".into(),
+        KeyCode::Key4 => "4".into(),
+        KeyCode::Key5 => "5".into(),
+        KeyCode::Key6 => "6".into(),
+        KeyCode::Key7 => "7".into(),
+        KeyCode::Key8 => "8".into(),
+        KeyCode::Key9 => "9".into(),
+        KeyCode::Escape => "ESC".into(),
+        key => format!("{key:?}").to_uppercase(),
+    }
+}
+
+/// What the player wants to do this frame.
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct ActionState {
+    /// Both axes go from -1 to 1.
+    pub movement: Vec2,
+    pub pressed: u32,
+    pub just_pressed: u32,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed & action.bit() != 0
+    }
+
+    /// For inputs without key bindings, like touches.
+    pub fn press(&mut self, action: Action) {
+        self.pressed |= action.bit();
+    }
+
+    pub fn just_press(&mut self, action: Action) {
+        self.pressed |= action.bit();
+        self.just_pressed |= action.bit();
+    }
+
+    pub fn any_just_pressed(&self, actions: impl IntoIterator<Item = Action>) -> bool {
+        actions.into_iter().any(|action| self.just_pressed(action))
+    }
+}
+
+/// What the second player wants to do this frame, only their ship actions are used.
+#[derive(Resource, Default, Clone, PartialEq, Debug, Deref, DerefMut)]
+pub struct SecondPlayerActions(pub ActionState);
+
+/// In co-op the first gamepad belongs to the first player and all others to the second one.
+/// Every gamepad can still use the menus.
+pub fn collect_actions(
+    input: PlayerInput,
+    bindings: Res<KeyBindings>,
+    mode: Res<GameMode>,
+    gamepads: Res<Gamepads>,
+    mut actions: ResMut<ActionState>,
+    mut second_actions: ResMut<SecondPlayerActions>,
+) {
+    let mut gamepads: Vec<_> = gamepads.iter().collect();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+    let split = match *mode {
+        GameMode::Solo => gamepads.len(),
+        GameMode::Coop => gamepads.len().min(1),
+    };
+    let (first_gamepads, second_gamepads) = gamepads.split_at(split);
+
+    *actions = input.read(
+        |action| bindings.keys(action),
+        first_gamepads,
+        second_gamepads,
+    );
+    second_actions.0 = input.read(Action::player_two_keys, second_gamepads, &[]);
+}
+
+#[derive(SystemParam)]
+pub struct PlayerInput<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    button_input: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl PlayerInput<'_> {
+    /// Actions from `keys` and the buttons and sticks of `gamepads`, `menu_gamepads` only add the menu actions.
+    fn read<'k>(
+        &self,
+        keys: impl Fn(Action) -> &'k [KeyCode],
+        gamepads: &[Gamepad],
+        menu_gamepads: &[Gamepad],
+    ) -> ActionState {
+        let mut actions = ActionState::default();
+
+        for action in Action::ALL {
+            let keys = || keys(action).iter().copied();
+            let buttons = || {
+                let menu_gamepads = if action.steers_ship() {
+                    &[]
+                } else {
+                    menu_gamepads
+                };
+                gamepads
+                    .iter()
+                    .chain(menu_gamepads)
+                    .flat_map(move |gamepad| {
+                        action
+                            .buttons()
+                            .iter()
+                            .map(move |button_type| GamepadButton::new(*gamepad, *button_type))
+                    })
+            };
+
+            if self.keyboard_input.any_pressed(keys()) || self.button_input.any_pressed(buttons()) {
+                actions.pressed |= action.bit();
+            }
+            if self.keyboard_input.any_just_pressed(keys())
+                || self.button_input.any_just_pressed(buttons())
+            {
+                actions.just_pressed |= action.bit();
+            }
+        }
+
+        let axis = |negative, positive| {
+            actions.pressed(positive) as i8 as f32 - actions.pressed(negative) as i8 as f32
+        };
+        let mut movement = Vec2::new(
+            axis(Action::MoveLeft, Action::MoveRight),
+            axis(Action::MoveDown, Action::MoveUp),
+        );
+
+        for gamepad in gamepads {
+            let stick = Vec2::new(
+                self.axes
+                    .get(GamepadAxis::new(*gamepad, GamepadAxisType::LeftStickX))
+                    .unwrap_or(0.0),
+                self.axes
+                    .get(GamepadAxis::new(*gamepad, GamepadAxisType::LeftStickY))
+                    .unwrap_or(0.0),
+            );
+            movement += apply_deadzone(stick);
+        }
+
+        actions.movement = movement.clamp(Vec2::NEG_ONE, Vec2::ONE);
+        actions
+    }
+}
+
+/// Ignores small stick movements and rescales the rest, so movement still starts at zero.
+fn apply_deadzone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length < STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+
+    let scaled = ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
+    stick / length * scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{apply_deadzone, Action, KeyBindings, STICK_DEADZONE};
+
+    #[test]
+    fn small_stick_movement_is_ignored() {
+        assert_eq!(apply_deadzone(Vec2::new(0.1, -0.1)), Vec2::ZERO);
+        assert_eq!(apply_deadzone(Vec2::new(STICK_DEADZONE, 0.0)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn stick_movement_is_rescaled_past_the_deadzone() {
+        let full = apply_deadzone(Vec2::new(0.0, 1.0));
+        let half = apply_deadzone(Vec2::new((1.0 + STICK_DEADZONE) / 2.0, 0.0));
+
+        assert!((full - Vec2::new(0.0, 1.0)).length() < 0.001);
+        assert!((half - Vec2::new(0.5, 0.0)).length() < 0.001);
+    }
+
+    #[test]
+    fn binding_a_key_swaps_it_with_the_action_that_had_it() {
+        let mut bindings = KeyBindings::default();
+
+        bindings.bind(Action::Fire, KeyCode::Space);
+        bindings.bind(Action::MoveLeft, KeyCode::Q);
+
+        assert_eq!(bindings.keys(Action::Fire), [KeyCode::Space]);
+        assert_eq!(bindings.keys(Action::MoveLeft), [KeyCode::Q]);
+        assert_eq!(bindings.keys(Action::PreviousWeapon), [KeyCode::A]);
+    }
+
+    #[test]
+    fn keys_of_the_second_player_and_the_menu_cannot_be_bound() {
+        let mut bindings = KeyBindings::default();
+
+        assert!(!bindings.bind(Action::Fire, KeyCode::RControl));
+        assert!(!bindings.bind(Action::MoveUp, KeyCode::Up));
+        assert!(!bindings.bind(Action::Fire, KeyCode::R));
+        assert!(!bindings.bind(Action::Pause, KeyCode::Return));
+
+        assert_eq!(bindings, KeyBindings::default());
+    }
+
+    #[test]
+    fn saved_bindings_keep_defaults_for_missing_actions() {
+        let loaded = KeyBindings::from_ron("(keys: { Fire: [K] })");
+
+        assert_eq!(loaded.keys(Action::Fire), [KeyCode::K]);
+        assert_eq!(loaded.keys(Action::MoveUp), [KeyCode::W]);
+        assert_eq!(KeyBindings::from_ron(&loaded.to_ron()), loaded);
+    }
+}
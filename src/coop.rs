@@ -0,0 +1,189 @@
+//! Two players on one machine. The second one has its own ship, health, weapons and score,
+//! steered with the arrow keys or the second gamepad, see `controls`.
+
+use bevy::prelude::*;
+
+use crate::{
+    collision::{build_collision_masks, Collider, CollisionMasks},
+    shooting::WeaponCatalog,
+    simulation::Interpolated,
+    AppState, Layers, MyAssets, Player,
+};
+
+/// Ships of the second player are tinted, so both can tell which one is theirs.
+const PLAYER_TWO_COLOR: Color = Color::rgb(0.6, 1.0, 0.7);
+/// Distance between the ships of both players when a game starts.
+const START_SPACING: f32 = 300.0;
+
+pub struct CoopPlugin;
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .add_system(
+                spawn_players
+                    .after(build_collision_masks)
+                    .in_schedule(OnExit(AppState::Loading)),
+            )
+            .add_system(spawn_players.in_schedule(OnExit(AppState::MainMenu)));
+    }
+}
+
+/// Picked in the main menu before every game.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    #[default]
+    Solo,
+    Coop,
+}
+
+impl GameMode {
+    pub fn players(self) -> usize {
+        match self {
+            GameMode::Solo => 1,
+            GameMode::Coop => 2,
+        }
+    }
+}
+
+/// Where the player with `index` starts, side by side in the middle of the playfield.
+pub fn start_position(index: usize, players: usize) -> Vec3 {
+    let x = (index as f32 - (players as f32 - 1.0) / 2.0) * START_SPACING;
+    Vec3::new(x, 0.0, Layers::Actors.order_nr())
+}
+
+/// Spawns the ships missing for the `GameMode` and removes the ones left over from the last game.
+fn spawn_players(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mut player_query: Query<(Entity, &Player, &mut Transform, &mut Interpolated)>,
+    my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+    images: Res<Assets<Image>>,
+    masks: Res<CollisionMasks>,
+) {
+    let players = mode.players();
+    let weapons = weapon_catalogs.get(&my_assets.weapons).unwrap();
+
+    let mut spawned = vec![false; players];
+    for (entity, player, mut transform, mut interpolated) in &mut player_query {
+        if player.index >= players {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        spawned[player.index] = true;
+        transform.translation = start_position(player.index, players);
+        *interpolated = Interpolated::new(transform.translation);
+    }
+
+    for (index, _) in spawned.iter().enumerate().filter(|(_, spawned)| !**spawned) {
+        let translation = start_position(index, players);
+        let color = if index == 0 {
+            Color::WHITE
+        } else {
+            PLAYER_TWO_COLOR
+        };
+
+        commands.spawn((
+            SpriteBundle {
+                texture: my_assets.player.clone(),
+                sprite: Sprite { color, ..default() },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            Player::new(index, weapons.player_weapons()),
+            Collider::from_image(&images, &masks, &my_assets.player),
+            Interpolated::new(translation),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{start_position, GameMode};
+    use crate::{
+        shooting::Projectile, simulation::Interpolated, test_harness::TestGame, Player,
+        PLAYER_HEALTH,
+    };
+
+    #[test]
+    fn solo_player_starts_in_the_middle() {
+        assert_eq!(start_position(0, 1).truncate(), Vec2::ZERO);
+        assert_eq!(
+            start_position(0, 2).truncate(),
+            -start_position(1, 2).truncate()
+        );
+    }
+
+    #[test]
+    fn coop_spawns_two_players() {
+        let mut game = TestGame::with_mode(GameMode::Coop);
+
+        let players = game.players();
+
+        assert_eq!(players.len(), 2);
+        for (index, player) in players.into_iter().enumerate() {
+            let player = game.app.world.get::<Player>(player).unwrap();
+            assert_eq!(player.index, index);
+            assert_eq!(player.health, PLAYER_HEALTH);
+        }
+    }
+
+    #[test]
+    fn each_player_moves_with_their_own_keys() {
+        let mut game = TestGame::with_mode(GameMode::Coop);
+        let players = game.players();
+        let start: Vec<_> = players
+            .iter()
+            .map(|player| game.position(*player))
+            .collect();
+
+        game.press(KeyCode::Left);
+        game.step_frames(10);
+
+        assert_eq!(game.position(players[0]), start[0]);
+        assert!(game.position(players[1]).x < start[1].x);
+    }
+
+    #[test]
+    fn enemies_aim_at_the_nearest_player() {
+        let mut game = TestGame::with_mode(GameMode::Coop);
+        let [first, second] = game.players()[..] else {
+            panic!("Co-op has two players");
+        };
+        let enemy_position = game.position(second).truncate() + Vec2::new(0.0, 200.0);
+        let first_start = game.position(first);
+        // Nearer to the enemy than the second player, but out of its line of fire.
+        move_player(
+            &mut game,
+            first,
+            enemy_position.extend(0.0) + Vec3::new(180.0, 0.0, 0.0),
+        );
+
+        game.spawn_enemy("space_crusader", enemy_position);
+        game.step();
+        assert_eq!(hostile_projectiles(&mut game), 0);
+
+        move_player(&mut game, first, first_start);
+        game.step();
+        assert!(hostile_projectiles(&mut game) > 0);
+    }
+
+    fn move_player(game: &mut TestGame, player: Entity, position: Vec3) {
+        let mut entity = game.app.world.entity_mut(player);
+        let z = entity.get::<Transform>().unwrap().translation.z;
+        let position = position.truncate().extend(z);
+        entity.get_mut::<Transform>().unwrap().translation = position;
+        *entity.get_mut::<Interpolated>().unwrap() = Interpolated::new(position);
+    }
+
+    fn hostile_projectiles(game: &mut TestGame) -> usize {
+        let mut projectiles = game.app.world.query::<&Projectile>();
+        projectiles
+            .iter(&game.app.world)
+            .filter(|projectile| !projectile.friendly)
+            .count()
+    }
+}
@@ -1,83 +1,714 @@
-use std::time::Duration;
+use std::collections::BTreeMap;
 
-use bevy::{prelude::*, window::PrimaryWindow};
-use rand::Rng;
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    prelude::*,
+    reflect::TypeUuid,
+    time::Stopwatch,
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
 
-use crate::{AppState, Enemy, Layers, MyAssets, ORIGINAL_TARGET_FPS};
-
-const MIN_SPAWN_SECONDS: f32 = 1.0;
-const MAX_SPAWN_SECONDS: f32 = 5.5;
+use crate::{
+    boss::{self, BossArchetype, BossDefeated},
+    collision::{Collider, CollisionMasks},
+    formation::{Formation, Squad, SquadMember},
+    movement::{Movement, MovementPattern},
+    playfield::PLAYFIELD,
+    rng::GameRng,
+    ron_asset::{resolve_handle, RonAsset, RonAssetPlugin},
+    shooting::WeaponCatalog,
+    simulation::{Interpolated, TickSet},
+    AppState, Enemy, Game, Layers, MyAssets,
+};
 
 pub struct EnemySpawningPlugin;
 
 impl Plugin for EnemySpawningPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(setup_enemy_spawning.in_schedule(OnExit(AppState::Loading)))
-            .add_systems((spawn_enemy, enemy_movement).in_set(OnUpdate(AppState::InGame)));
+        app.add_plugin(RonAssetPlugin::<EnemyArchetypes>::default())
+            .add_plugin(RonAssetPlugin::<WaveScript>::default())
+            .add_event::<WaveClearedEvent>()
+            .add_system(check_references.in_schedule(OnExit(AppState::Loading)))
+            .add_system(setup_enemy_spawning.in_schedule(OnExit(AppState::Loading)))
+            .add_system(setup_enemy_spawning.in_schedule(OnExit(AppState::GameOver)))
+            .add_system(setup_enemy_spawning.in_schedule(OnExit(AppState::GameWon)))
+            .add_system(
+                spawn_enemy
+                    .after(boss::check_boss_defeated)
+                    .in_set(TickSet::Gameplay)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// All enemy ships, loaded from `assets/data/fleet.enemies.ron`.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "4416c0f3-6841-4a1a-8e09-269874a45a9d"]
+pub struct EnemyArchetypes {
+    pub archetypes: BTreeMap<String, EnemyArchetype>,
+    #[serde(default)]
+    pub bosses: BTreeMap<String, BossArchetype>,
+}
+
+impl RonAsset for EnemyArchetypes {
+    const EXTENSIONS: &'static [&'static str] = &["enemies.ron"];
+
+    fn resolve_dependencies(&mut self, load_context: &LoadContext) -> Vec<AssetPath<'static>> {
+        let mut dependencies = Vec::new();
+        for archetype in self.archetypes.values_mut() {
+            archetype.image = resolve_handle(&archetype.sprite, load_context, &mut dependencies);
+        }
+        for boss in self.bosses.values_mut() {
+            boss.image = resolve_handle(&boss.sprite, load_context, &mut dependencies);
+        }
+        dependencies
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some((key, _)) = self
+            .archetypes
+            .iter()
+            .find(|(_, archetype)| archetype.speed.0 > archetype.speed.1)
+        {
+            return Err(format!(
+                "The minimum speed of `{key}` is above its maximum speed."
+            ));
+        }
+        if self
+            .archetypes
+            .values()
+            .all(|archetype| archetype.spawn_weight == 0)
+        {
+            return Err("At least one enemy archetype needs a spawn weight above 0.".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl EnemyArchetypes {
+    /// Picks the key of a random archetype according to the spawn weights.
+    pub fn random_key(&self, rng: &mut impl Rng) -> &str {
+        let weights = WeightedIndex::new(
+            self.archetypes
+                .values()
+                .map(|archetype| archetype.spawn_weight),
+        )
+        .unwrap();
+        let index = weights.sample(rng);
+
+        self.archetypes.keys().nth(index).unwrap()
+    }
+
+    pub fn get(&self, key: &str) -> &EnemyArchetype {
+        self.archetypes
+            .get(key)
+            .unwrap_or_else(|| panic!("No enemy archetype named `{key}`."))
+    }
+
+    pub fn boss(&self, key: &str) -> &BossArchetype {
+        self.bosses
+            .get(key)
+            .unwrap_or_else(|| panic!("No boss named `{key}`."))
+    }
+
+    pub fn enemy(&self, key: &str, weapons: &WeaponCatalog, rng: &mut impl Rng) -> Enemy {
+        let archetype = self.get(key);
+        let (min_speed, max_speed) = archetype.speed;
+
+        Enemy {
+            health: archetype.health,
+            speed: rng.gen_range(min_speed..=max_speed),
+            collision_damage: archetype.collision_damage,
+            bounty: archetype.bounty,
+            weapon: archetype
+                .weapon
+                .as_ref()
+                .map(|weapon| weapons.weapon(weapon, false)),
+        }
+    }
+
+    pub fn collider(&self, key: &str, images: &Assets<Image>, masks: &CollisionMasks) -> Collider {
+        let archetype = self.get(key);
+        let collider = Collider::from_image(images, masks, &archetype.image);
+
+        match archetype.hitbox {
+            Some((width, height)) => collider.with_hitbox(Vec2::new(width, height)),
+            None => collider,
+        }
+    }
+
+    /// Every weapon of the ships and bosses has to be in the catalog.
+    pub fn check_weapons(&self, weapons: &WeaponCatalog) -> Result<(), String> {
+        let archetype_weapons = self
+            .archetypes
+            .iter()
+            .filter_map(|(key, archetype)| Some((key, archetype.weapon.as_ref()?)));
+        let boss_weapons = self.bosses.iter().flat_map(|(key, boss)| {
+            boss.phases
+                .iter()
+                .filter_map(move |phase| Some((key, phase.weapon.as_ref()?)))
+        });
+
+        for (key, weapon) in archetype_weapons.chain(boss_weapons) {
+            if !weapons.weapons.contains_key(weapon) {
+                return Err(format!(
+                    "`{key}` uses the weapon `{weapon}`, which is not in the weapon catalog."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn images(&self) -> impl Iterator<Item = &Handle<Image>> {
+        self.archetypes
+            .values()
+            .map(|archetype| &archetype.image)
+            .chain(self.bosses.values().map(|boss| &boss.image))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EnemyArchetype {
+    pub sprite: String,
+    #[serde(skip)]
+    pub image: Handle<Image>,
+    pub health: u32,
+    /// Min and max speed, each ship gets a random speed in between.
+    pub speed: (f32, f32),
+    pub collision_damage: u32,
+    pub bounty: u32,
+    /// Key of the weapon in the `WeaponCatalog`.
+    pub weapon: Option<String>,
+    /// Relative chance of being picked by the random spawner.
+    pub spawn_weight: u32,
+    /// Width and height of the area that can be hit, the whole sprite if missing.
+    #[serde(default)]
+    pub hitbox: Option<(f32, f32)>,
+    #[serde(default)]
+    pub movement: MovementPattern,
+}
+
+/// The waves of every level, loaded from `assets/data/campaign.waves.ron`.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "cd3fb678-4a7d-41cb-9fe6-2a3d3e8fbe55"]
+pub struct WaveScript {
+    pub levels: Vec<LevelScript>,
+}
+
+impl RonAsset for WaveScript {
+    const EXTENSIONS: &'static [&'static str] = &["waves.ron"];
+
+    fn validate(&self) -> Result<(), String> {
+        if self.levels.is_empty() {
+            return Err("The wave script needs at least one level.".to_string());
+        }
+        for (level, script) in self.levels.iter().enumerate() {
+            let level = level + 1;
+            for entry in script.waves.iter().flat_map(|wave| &wave.entries) {
+                if entry.count == 0 {
+                    return Err(format!("A wave of level {level} spawns no ships."));
+                }
+                for seconds in [entry.delay, entry.interval] {
+                    if !seconds.is_finite() || seconds < 0.0 {
+                        return Err(format!(
+                            "Delays and intervals of level {level} have to be zero or more seconds."
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WaveScript {
+    /// Every ship and boss of the script has to be in the fleet.
+    pub fn check_ships(&self, enemies: &EnemyArchetypes) -> Result<(), String> {
+        for (level, script) in self.levels.iter().enumerate() {
+            let level = level + 1;
+            let archetypes = script
+                .waves
+                .iter()
+                .flat_map(|wave| &wave.entries)
+                .filter_map(|entry| entry.archetype.as_ref());
+            for key in archetypes {
+                if !enemies.archetypes.contains_key(key) {
+                    return Err(format!(
+                        "Level {level} spawns `{key}`, which is not in the enemy archetypes."
+                    ));
+                }
+            }
+            if let Some(key) = script
+                .boss
+                .as_ref()
+                .filter(|key| !enemies.bosses.contains_key(*key))
+            {
+                return Err(format!(
+                    "Level {level} ends with the boss `{key}`, which is not in the enemy archetypes."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Levels without a script of their own repeat the last scripted level.
+    pub fn level(&self, level: u32) -> &LevelScript {
+        let index = (level as usize).saturating_sub(1);
+        self.levels
+            .get(index)
+            .or(self.levels.last())
+            .expect("The wave script needs at least one level.")
     }
 }
 
+#[derive(Deserialize)]
+pub struct LevelScript {
+    /// Played in order, starting over once the last wave is cleared.
+    pub waves: Vec<Wave>,
+    /// Key of the boss in `EnemyArchetypes`, fought before moving on to the next level.
+    #[serde(default)]
+    pub boss: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Wave {
+    pub entries: Vec<WaveEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct WaveEntry {
+    /// Seconds after the start of the wave until the first ship appears.
+    #[serde(default)]
+    pub delay: f32,
+    /// Key in `EnemyArchetypes`, picked randomly by spawn weight for each ship if missing.
+    #[serde(default)]
+    pub archetype: Option<String>,
+    pub count: u32,
+    /// Seconds between two ships of this entry.
+    #[serde(default)]
+    pub interval: f32,
+    #[serde(default)]
+    pub position: SpawnPosition,
+    /// Replaces the movement of the archetype for the ships of this entry.
+    #[serde(default)]
+    pub movement: Option<MovementPattern>,
+    /// Spawns a whole squad of ships each time instead of a single one, `count` is the number of squads.
+    #[serde(default)]
+    pub formation: Option<Formation>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+pub enum SpawnPosition {
+    #[default]
+    Random,
+    /// Horizontal position, from 0.0 (left edge) to 1.0 (right edge).
+    At(f32),
+    /// Ships of the entry are spread evenly across the screen.
+    Spread,
+}
+
+/// Plays the wave script, following the level of the player.
 #[derive(Resource)]
-struct EnemySpawnConfig {
-    timer: Timer,
+pub struct WaveDirector {
+    pub level: u32,
+    pub wave: usize,
+    /// Time since the current wave started.
+    elapsed: Stopwatch,
+    /// Ships spawned so far for each entry of the current wave.
+    spawned: Vec<u32>,
+    /// No waves are spawned while the boss of the level is around.
+    fighting_boss: bool,
+}
+
+impl WaveDirector {
+    pub fn new(level: u32) -> Self {
+        Self {
+            level,
+            wave: 0,
+            elapsed: Stopwatch::new(),
+            spawned: Vec::new(),
+            fighting_boss: false,
+        }
+    }
+
+    fn start_wave(&mut self, level: u32, wave: usize) {
+        self.level = level;
+        self.wave = wave;
+        self.elapsed.reset();
+        self.spawned.clear();
+    }
 }
 
+/// Sent once every ship of a wave has been spawned and got destroyed or escaped.
+pub struct WaveClearedEvent {
+    pub level: u32,
+    pub wave: usize,
+}
+
+/// Each data file is checked on its own while loading,
+/// what they reference in each other only once all of them are there.
+fn check_references(
+    my_assets: Res<MyAssets>,
+    weapons: Res<Assets<WeaponCatalog>>,
+    enemies: Res<Assets<EnemyArchetypes>>,
+    waves: Res<Assets<WaveScript>>,
+) {
+    let weapons = weapons.get(&my_assets.weapons).unwrap();
+    let enemies = enemies.get(&my_assets.enemies).unwrap();
+    let waves = waves.get(&my_assets.waves).unwrap();
+
+    if let Err(err) = enemies.check_weapons(weapons) {
+        panic!("Invalid `fleet.enemies.ron`: {err}");
+    }
+    if let Err(err) = waves.check_ships(enemies) {
+        panic!("Invalid `campaign.waves.ron`: {err}");
+    }
+}
+
+/// Also starts over from the first level when a new game starts.
 fn setup_enemy_spawning(mut commands: Commands) {
-    commands.insert_resource(EnemySpawnConfig {
-        timer: Timer::new(
-            Duration::from_secs_f32(random_spawn_time()),
-            TimerMode::Once,
-        ),
-    })
+    commands.insert_resource(WaveDirector::new(1));
 }
 
 fn spawn_enemy(
     mut commands: Commands,
-    time: Res<Time>,
-    mut config: ResMut<EnemySpawnConfig>,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
+    fixed_time: Res<FixedTime>,
+    game: Res<Game>,
+    mut director: ResMut<WaveDirector>,
+    mut ev_wavecleared: EventWriter<WaveClearedEvent>,
+    mut ev_bossdefeated: EventReader<BossDefeated>,
+    enemies_query: Query<(), With<Enemy>>,
     assets: Res<Assets<Image>>,
+    masks: Res<CollisionMasks>,
     my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+    enemy_archetypes: Res<Assets<EnemyArchetypes>>,
+    wave_scripts: Res<Assets<WaveScript>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let window = primary_query.single();
+    for ev in ev_bossdefeated.iter() {
+        director.fighting_boss = false;
+        director.start_wave(ev.level + 1, 0);
+    }
+    if director.fighting_boss {
+        return;
+    }
+
+    let weapons = weapon_catalogs.get(&my_assets.weapons).unwrap();
+    let enemies = enemy_archetypes.get(&my_assets.enemies).unwrap();
+    let level = wave_scripts
+        .get(&my_assets.waves)
+        .unwrap()
+        .level(director.level);
+
+    director.elapsed.tick(fixed_time.period);
+    // Hot reloads can take waves away, levels without any have nothing to spawn.
+    let Some(wave) = level.waves.get(director.wave) else {
+        if director.wave > 0 {
+            let level = director.level;
+            director.start_wave(level, 0);
+        }
+        return;
+    };
+    director.spawned.resize(wave.entries.len(), 0);
+
+    for (i, entry) in wave.entries.iter().enumerate() {
+        while director.spawned[i] < entry.count
+            && director.elapsed.elapsed_secs()
+                >= entry.delay + director.spawned[i] as f32 * entry.interval
+        {
+            let key = match &entry.archetype {
+                Some(key) => key.as_str(),
+                None => enemies.random_key(&mut rng.gameplay),
+            };
+            let enemy = enemies.enemy(key, weapons, &mut rng.gameplay);
+            let movement = entry
+                .movement
+                .clone()
+                .unwrap_or_else(|| enemies.get(key).movement.clone());
+            let img_handle = enemies.get(key).image.clone();
+            let collider = enemies.collider(key, &assets, &masks);
+            let img_size = collider.size;
+            let offsets = match &entry.formation {
+                Some(formation) => formation.offsets(),
+                None => vec![Vec2::ZERO],
+            };
 
-    config.timer.tick(time.delta());
+            // The outer ships of a formation have to fit on the playfield as well, even when
+            // swaying to the side. Formations wider than the playfield are centered.
+            let half_width = offsets
+                .iter()
+                .map(|offset| offset.x.abs())
+                .fold(0.0, f32::max)
+                + img_size.x / 2.
+                + movement.sway_width();
+            let max_x_offset = (PLAYFIELD.x / 2.0 - half_width).max(0.0);
+            let min_x_offset = -max_x_offset;
 
-    if config.timer.finished() {
-        config.timer = Timer::new(
-            Duration::from_secs_f32(random_spawn_time()),
-            TimerMode::Once,
-        );
+            let x = match entry.position {
+                SpawnPosition::Random if max_x_offset > 0.0 => {
+                    rng.gameplay.gen_range(min_x_offset..max_x_offset)
+                }
+                SpawnPosition::Random => 0.0,
+                SpawnPosition::At(fraction) => {
+                    min_x_offset + (max_x_offset - min_x_offset) * fraction.clamp(0.0, 1.0)
+                }
+                SpawnPosition::Spread => {
+                    let fraction = (director.spawned[i] + 1) as f32 / (entry.count + 1) as f32;
+                    min_x_offset + (max_x_offset - min_x_offset) * fraction
+                }
+            };
 
-        let enemy = Enemy::random();
-        let img_handle = enemy.clone().image(my_assets);
-        let img_size = assets.get(&img_handle).unwrap().size();
+            let position = Vec2::new(x, (PLAYFIELD.y / 2.) + (img_size.y / 2.));
+            let speed = enemy.speed;
+            let mut enemy = Some(enemy);
+            let mut ships = Vec::new();
+            for offset in &offsets {
+                let enemy = enemy
+                    .take()
+                    .unwrap_or_else(|| enemies.enemy(key, weapons, &mut rng.gameplay));
+                let translation = (position + *offset).extend(Layers::Actors.order_nr());
 
-        let min_x_offset = -(window.width() / 2.0) + (img_size.x / 2.);
-        let max_x_offset = window.width() / 2.0 - (img_size.x / 2.);
+                ships.push(
+                    commands
+                        .spawn((
+                            SpriteBundle {
+                                texture: img_handle.clone(),
+                                transform: Transform::from_translation(translation),
+                                ..default()
+                            },
+                            enemy,
+                            collider.clone(),
+                            Interpolated::new(translation),
+                        ))
+                        .id(),
+                );
+            }
 
-        commands.spawn((
-            SpriteBundle {
-                // TODO: Do not clone here.
-                texture: img_handle,
-                transform: Transform::from_xyz(
-                    rand::thread_rng().gen_range(min_x_offset..max_x_offset),
-                    (window.height() / 2.) + (img_size.y / 2.),
-                    Layers::Actors.order_nr(),
-                ),
-                ..default()
-            },
-            enemy,
-        ));
+            match &entry.formation {
+                Some(formation) => {
+                    let squad = commands.spawn(Movement::new(movement)).id();
+                    for (&ship, &offset) in ships.iter().zip(&offsets) {
+                        commands.entity(ship).insert(SquadMember { squad, offset });
+                    }
+                    commands.entity(squad).insert(Squad::new(
+                        ships,
+                        position,
+                        speed,
+                        max_x_offset,
+                        formation.bonus,
+                    ));
+                }
+                None => {
+                    commands.entity(ships[0]).insert(Movement::new(movement));
+                }
+            }
+
+            director.spawned[i] += 1;
+        }
     }
-}
 
-fn random_spawn_time() -> f32 {
-    rand::thread_rng().gen_range(MIN_SPAWN_SECONDS..=MAX_SPAWN_SECONDS)
+    let all_spawned = wave
+        .entries
+        .iter()
+        .zip(&director.spawned)
+        .all(|(entry, spawned)| *spawned >= entry.count);
+
+    if all_spawned && enemies_query.is_empty() {
+        ev_wavecleared.send(WaveClearedEvent {
+            level: director.level,
+            wave: director.wave,
+        });
+
+        if game.level != director.level {
+            match &level.boss {
+                Some(key) => {
+                    boss::spawn_boss(
+                        &mut commands,
+                        key,
+                        director.level,
+                        enemies,
+                        weapons,
+                        &assets,
+                        &masks,
+                    );
+                    director.fighting_boss = true;
+                }
+                None => director.start_wave(game.level, 0),
+            }
+        } else {
+            let next_wave = (director.wave + 1) % level.waves.len();
+            director.start_wave(game.level, next_wave);
+        }
+    }
 }
 
-fn enemy_movement(time: Res<Time>, mut sprite_position: Query<(&mut Enemy, &mut Transform)>) {
-    for (enemy, mut transform) in &mut sprite_position {
-        transform.translation.y -= enemy.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, prelude::*};
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{EnemyArchetypes, WaveClearedEvent, WaveDirector, WaveScript};
+    use crate::{ron_asset::RonAsset, shooting::WeaponCatalog, test_harness::TestGame, Enemy};
+
+    fn two_trespassers() -> WaveScript {
+        ron::from_str(
+            r#"#![enable(implicit_some)]
+            (
+                levels: [
+                    (waves: [(entries: [(archetype: "trespasser", count: 2, interval: 1.0)])]),
+                ],
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn enemy_positions(game: &mut TestGame) -> Vec<Vec3> {
+        game.app
+            .world
+            .query_filtered::<&Transform, With<Enemy>>()
+            .iter(&game.app.world)
+            .map(|transform| transform.translation)
+            .collect()
+    }
+
+    fn fleet(speed: (f32, f32)) -> EnemyArchetypes {
+        ron::from_str(&format!(
+            r#"(archetypes: {{"drone": (
+                sprite: "ships/trespasser.png",
+                health: 10,
+                speed: {speed:?},
+                collision_damage: 1,
+                bounty: 1,
+                weapon: None,
+                spawn_weight: 1,
+            )}})"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn ships_can_have_a_fixed_speed() {
+        let weapons: WeaponCatalog =
+            ron::from_str(include_str!("../assets/data/arsenal.weapons.ron")).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let enemy = fleet((1.5, 1.5)).enemy("drone", &weapons, &mut rng);
+
+        assert_eq!(enemy.speed, 1.5);
+        assert!(fleet((1.5, 1.5)).validate().is_ok());
+        assert!(fleet((2.0, 1.0)).validate().is_err());
+    }
+
+    #[test]
+    fn fleets_that_would_crash_the_game_are_rejected() {
+        let weapons: WeaponCatalog =
+            ron::from_str(include_str!("../assets/data/arsenal.weapons.ron")).unwrap();
+        let fleet = || -> EnemyArchetypes {
+            ron::from_str(include_str!("../assets/data/fleet.enemies.ron")).unwrap()
+        };
+        assert!(fleet().validate().is_ok());
+        assert!(fleet().check_weapons(&weapons).is_ok());
+
+        let mut unpickable = fleet();
+        for archetype in unpickable.archetypes.values_mut() {
+            archetype.spawn_weight = 0;
+        }
+        assert!(unpickable.validate().is_err());
+
+        let mut unarmed = fleet();
+        let archetype = unarmed.archetypes.values_mut().next().unwrap();
+        archetype.weapon = Some("no_such_weapon".to_string());
+        assert!(unarmed.check_weapons(&weapons).is_err());
+    }
+
+    #[test]
+    fn wave_scripts_that_would_crash_the_game_are_rejected() {
+        let fleet: EnemyArchetypes =
+            ron::from_str(include_str!("../assets/data/fleet.enemies.ron")).unwrap();
+        let campaign: WaveScript =
+            ron::from_str(include_str!("../assets/data/campaign.waves.ron")).unwrap();
+        assert!(campaign.validate().is_ok());
+        assert!(campaign.check_ships(&fleet).is_ok());
+
+        let script = |level: &str| -> WaveScript {
+            ron::from_str(&format!("#![enable(implicit_some)]\n(levels: [{level}])")).unwrap()
+        };
+        assert!(script("").validate().is_err());
+        for entry in [
+            r#"(archetype: "trespasser", count: 0)"#,
+            r#"(archetype: "trespasser", count: 2, interval: -1.0)"#,
+            r#"(archetype: "trespasser", count: 2, delay: -1.0)"#,
+        ] {
+            let level = format!("(waves: [(entries: [{entry}])])");
+            assert!(script(&level).validate().is_err(), "{entry}");
+        }
+
+        let unknown_ship = script(r#"(waves: [(entries: [(archetype: "ufo", count: 2)])])"#);
+        assert!(unknown_ship.check_ships(&fleet).is_err());
+        let unknown_boss = script(r#"(waves: [], boss: "death_star")"#);
+        assert!(unknown_boss.check_ships(&fleet).is_err());
+    }
+
+    #[test]
+    fn missing_waves_start_the_level_over() {
+        let mut game = TestGame::with_waves(two_trespassers());
+        game.app.world.resource_mut::<WaveDirector>().wave = 3;
+        game.step();
+        assert_eq!(game.app.world.resource::<WaveDirector>().wave, 0);
+
+        let empty = ron::from_str("(levels: [(waves: [])])").unwrap();
+        let mut game = TestGame::with_waves(empty);
+        game.step_frames(5);
+        assert_eq!(game.enemy_count(), 0);
+    }
+
+    #[test]
+    fn same_seed_spawns_same_enemies() {
+        let campaign = || ron::from_str(include_str!("../assets/data/campaign.waves.ron")).unwrap();
+        let mut first = TestGame::with_seed(campaign(), 42);
+        let mut second = TestGame::with_seed(campaign(), 42);
+
+        first.step_frames(200);
+        second.step_frames(200);
+
+        assert!(!enemy_positions(&mut first).is_empty());
+        assert_eq!(enemy_positions(&mut first), enemy_positions(&mut second));
+    }
+
+    #[test]
+    fn wave_entries_spawn_over_time() {
+        let mut game = TestGame::with_waves(two_trespassers());
+        assert_eq!(game.enemy_count(), 1);
+
+        game.step_frames(40);
+
+        assert_eq!(game.enemy_count(), 2);
+    }
+
+    #[test]
+    fn wave_is_cleared_once_all_ships_are_gone() {
+        let mut game = TestGame::with_waves(two_trespassers());
+        game.step_frames(40);
+
+        let enemies: Vec<Entity> = game
+            .app
+            .world
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(&game.app.world)
+            .collect();
+        for enemy in enemies {
+            game.app.world.despawn(enemy);
+        }
+        game.step();
+
+        let events = game.app.world.resource::<Events<WaveClearedEvent>>();
+        let mut reader = events.get_reader();
+        let cleared: Vec<_> = reader.iter(events).collect();
+        assert_eq!(cleared.len(), 1);
+        assert_eq!((cleared[0].level, cleared[0].wave), (1, 0));
     }
 }
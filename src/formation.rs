@@ -0,0 +1,401 @@
+//! Squads of ships flying in formation. The squad leads: it flies the movement pattern and
+//! every ship keeps its spot in the formation. Shooting down the whole squad before any of
+//! its ships escapes pays a bonus.
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    collision::CollisionGridSystem,
+    enemy_collision,
+    lives::Respawning,
+    movement::{enemy_movement, Movement},
+    shooting::projectile_collision,
+    simulation::TickSet,
+    EnemyDestroyedEvent, Player,
+};
+
+pub struct FormationPlugin;
+
+impl Plugin for FormationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SquadWipedEvent>().add_systems(
+            (
+                squad_movement
+                    .after(enemy_movement)
+                    .before(CollisionGridSystem),
+                squad_bonus
+                    .after(projectile_collision)
+                    .after(enemy_collision),
+            )
+                .in_set(TickSet::Gameplay)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Part of a wave entry in `assets/data/campaign.waves.ron`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Formation {
+    pub shape: FormationShape,
+    /// Ships in each squad.
+    pub ships: u32,
+    /// Distance between neighbouring ships in pixels.
+    pub spacing: f32,
+    /// Paid for shooting down the whole squad.
+    pub bonus: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum FormationShape {
+    /// Pointing down, with the first ship at the tip.
+    V,
+    /// Side by side.
+    Line,
+    /// One behind the other.
+    Column,
+    /// Square-ish, filled row by row from the front.
+    Grid,
+    Circle,
+}
+
+impl Formation {
+    /// Spot of each ship relative to the squad, which is at the bottom center of the formation.
+    pub fn offsets(&self) -> Vec<Vec2> {
+        let ships = self.ships.max(1);
+        let spacing = self.spacing;
+
+        (0..ships)
+            .map(|i| match self.shape {
+                FormationShape::V => {
+                    let rank = i.div_ceil(2) as f32;
+                    let side = if i % 2 == 1 { -1.0 } else { 1.0 };
+                    Vec2::new(side * rank * spacing, rank * spacing)
+                }
+                FormationShape::Line => {
+                    Vec2::new((i as f32 - (ships - 1) as f32 / 2.0) * spacing, 0.0)
+                }
+                FormationShape::Column => Vec2::new(0.0, i as f32 * spacing),
+                FormationShape::Grid => {
+                    let columns = (ships as f32).sqrt().ceil() as u32;
+                    let (row, column) = (i / columns, i % columns);
+                    Vec2::new(
+                        (column as f32 - (columns - 1) as f32 / 2.0) * spacing,
+                        row as f32 * spacing,
+                    )
+                }
+                FormationShape::Circle => {
+                    // Neighbours are `spacing` apart along the circle.
+                    let radius = if ships > 1 {
+                        spacing / (2.0 * (PI / ships as f32).sin())
+                    } else {
+                        0.0
+                    };
+                    let angle = -FRAC_PI_2 + i as f32 * TAU / ships as f32;
+                    Vec2::new(angle.cos(), angle.sin() + 1.0) * radius
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Component)]
+pub struct Squad {
+    /// The ships still flying, in the order they were spawned.
+    pub ships: Vec<Entity>,
+    pub bonus: u32,
+    position: Vec2,
+    /// How fast the squad flies down, the speed of its first ship.
+    speed: f32,
+    /// How far from the center the squad can go before its outer ships leave the playfield.
+    max_x: f32,
+    /// A ship escaped, so there is no bonus.
+    broken: bool,
+    /// Who shot down the last ship so far.
+    last_hit_by: Option<Entity>,
+}
+
+impl Squad {
+    pub fn new(ships: Vec<Entity>, position: Vec2, speed: f32, max_x: f32, bonus: u32) -> Self {
+        Self {
+            ships,
+            bonus,
+            position,
+            speed,
+            max_x,
+            broken: false,
+            last_hit_by: None,
+        }
+    }
+}
+
+/// Ships of a squad hold their spot in the formation, even when pushed by a hit.
+#[derive(Component)]
+pub struct SquadMember {
+    pub squad: Entity,
+    pub offset: Vec2,
+}
+
+/// Sent once a whole squad has been shot down, with the player who got the bonus.
+pub struct SquadWipedEvent {
+    pub player: Entity,
+    pub bonus: u32,
+}
+
+fn squad_movement(
+    fixed_time: Res<FixedTime>,
+    mut squad_query: Query<(&mut Squad, &mut Movement)>,
+    mut ship_query: Query<(&SquadMember, &mut Transform), Without<Player>>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (mut squad, mut movement) in &mut squad_query {
+        let step = movement.step(
+            squad.position,
+            squad.speed,
+            squad.max_x,
+            delta,
+            player_query
+                .iter()
+                .map(|player| player.translation.truncate()),
+        );
+        squad.position += step;
+    }
+
+    for (member, mut transform) in &mut ship_query {
+        if let Ok((squad, _)) = squad_query.get(member.squad) {
+            let position = squad.position + member.offset;
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    }
+}
+
+/// Ships gone without being destroyed escaped, which breaks the squad.
+fn squad_bonus(
+    mut commands: Commands,
+    mut squad_query: Query<(Entity, &mut Squad)>,
+    ship_query: Query<(), With<SquadMember>>,
+    mut player_query: Query<&mut Player>,
+    mut ev_enemydestroyed: EventReader<EnemyDestroyedEvent>,
+    mut ev_squadwiped: EventWriter<SquadWipedEvent>,
+) {
+    let destroyed: Vec<&EnemyDestroyedEvent> = ev_enemydestroyed.iter().collect();
+
+    for (squad_entity, mut squad) in &mut squad_query {
+        let squad = &mut *squad;
+        for ev in &destroyed {
+            if squad.ships.contains(&ev.enemy) {
+                squad.ships.retain(|&ship| ship != ev.enemy);
+                squad.last_hit_by = ev.player;
+            }
+        }
+        let ships = squad.ships.len();
+        squad.ships.retain(|&ship| ship_query.contains(ship));
+        squad.broken |= squad.ships.len() < ships;
+
+        if !squad.ships.is_empty() {
+            continue;
+        }
+        commands.entity(squad_entity).despawn();
+        if squad.broken {
+            continue;
+        }
+        if let Some(player_entity) = squad.last_hit_by {
+            if let Ok(mut player) = player_query.get_mut(player_entity) {
+                player.score += squad.bonus;
+                ev_squadwiped.send(SquadWipedEvent {
+                    player: player_entity,
+                    bonus: squad.bonus,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, prelude::*};
+
+    use super::{Formation, FormationShape, Squad, SquadWipedEvent};
+    use crate::{
+        enemy_spawning::{SpawnPosition, WaveScript},
+        movement::MovementPattern,
+        playfield::PLAYFIELD,
+        shooting::{FiredBy, HitEffect, Projectile},
+        test_harness::TestGame,
+        EARTH_HEALTH,
+    };
+
+    fn squad_of_three() -> WaveScript {
+        ron::from_str(
+            r#"#![enable(implicit_some)]
+            (
+                levels: [
+                    (waves: [(entries: [(
+                        archetype: "trespasser",
+                        count: 2,
+                        interval: 60.0,
+                        position: At(0.5),
+                        formation: (shape: Line, ships: 3, spacing: 80.0, bonus: 500),
+                    )])]),
+                ],
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn squad_ships(game: &mut TestGame) -> Vec<Entity> {
+        game.app
+            .world
+            .query::<&Squad>()
+            .single(&game.app.world)
+            .ships
+            .clone()
+    }
+
+    /// Hits the ship with a shot of the player strong enough to destroy it.
+    fn shoot_down(game: &mut TestGame, ship: Entity) {
+        let player = game.player();
+        let projectile = Projectile {
+            speed: 0.0,
+            damage: 1000,
+            friendly: true,
+            hit_effect: HitEffect::Red,
+            pushback: 0.0,
+        };
+        let position = game.position(ship).truncate();
+        let projectile = game.spawn_projectile(projectile, position);
+        game.app
+            .world
+            .entity_mut(projectile)
+            .insert(FiredBy(player));
+        game.step();
+    }
+
+    fn wiped(game: &TestGame) -> Vec<u32> {
+        let events = game.app.world.resource::<Events<SquadWipedEvent>>();
+        let mut reader = events.get_reader();
+        reader.iter(events).map(|ev| ev.bonus).collect()
+    }
+
+    #[test]
+    fn formations_keep_their_shape() {
+        let formation = |shape| Formation {
+            shape,
+            ships: 5,
+            spacing: 10.0,
+            bonus: 0,
+        };
+
+        assert_eq!(
+            formation(FormationShape::V).offsets(),
+            [
+                (0.0, 0.0),
+                (-10.0, 10.0),
+                (10.0, 10.0),
+                (-20.0, 20.0),
+                (20.0, 20.0)
+            ]
+            .map(|(x, y)| Vec2::new(x, y))
+        );
+        assert_eq!(
+            formation(FormationShape::Line).offsets(),
+            [-20.0, -10.0, 0.0, 10.0, 20.0].map(|x| Vec2::new(x, 0.0))
+        );
+        assert_eq!(
+            formation(FormationShape::Grid).offsets()[3],
+            Vec2::new(-10.0, 10.0)
+        );
+
+        let circle = formation(FormationShape::Circle).offsets();
+        assert!(circle[0].length() < 0.001);
+        assert!((circle[0].distance(circle[1]) - 10.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn squad_flies_in_formation() {
+        let mut game = TestGame::with_waves(squad_of_three());
+        game.step_frames(50);
+
+        let ships = squad_ships(&mut game);
+        assert_eq!(ships.len(), 3);
+        let positions: Vec<Vec3> = ships.iter().map(|&ship| game.position(ship)).collect();
+        assert_eq!(positions[0].y, positions[1].y);
+        assert_eq!(positions[1].x - positions[0].x, 80.0);
+        assert_eq!(positions[2].x - positions[1].x, 80.0);
+    }
+
+    #[test]
+    fn wiping_out_a_squad_pays_a_bonus() {
+        let mut game = TestGame::with_waves(squad_of_three());
+        game.step_frames(50);
+
+        for ship in squad_ships(&mut game) {
+            shoot_down(&mut game, ship);
+            assert!(!game.exists(ship));
+        }
+
+        assert_eq!(wiped(&game), [500]);
+        assert_eq!(game.player_state().score, 3 * 35 + 500);
+    }
+
+    #[test]
+    fn no_bonus_once_a_ship_escaped() {
+        // In a column off to the side, the first ship flies past the player and off the bottom first.
+        let mut waves = squad_of_three();
+        let entry = &mut waves.levels[0].waves[0].entries[0];
+        entry.position = SpawnPosition::At(0.0);
+        entry.movement = Some(MovementPattern::Straight);
+        entry.formation.as_mut().unwrap().shape = FormationShape::Column;
+        let mut game = TestGame::with_waves(waves);
+        game.step();
+
+        let ships = squad_ships(&mut game);
+        for _ in 0..1000 {
+            if !game.exists(ships[0]) {
+                break;
+            }
+            game.step();
+        }
+        assert!(!game.exists(ships[0]));
+        assert!(game.game().earth_health < EARTH_HEALTH);
+        for &ship in &ships[1..] {
+            shoot_down(&mut game, ship);
+        }
+
+        assert!(wiped(&game).is_empty());
+        assert_eq!(game.player_state().score, 2 * 35);
+        assert_eq!(game.enemy_count(), 0);
+    }
+
+    #[test]
+    fn formations_wider_than_the_playfield_are_centered() {
+        let mut waves = squad_of_three();
+        let entry = &mut waves.levels[0].waves[0].entries[0];
+        entry.position = SpawnPosition::Random;
+        entry.movement = Some(MovementPattern::Straight);
+        entry.formation.as_mut().unwrap().spacing = PLAYFIELD.x;
+        let mut game = TestGame::with_waves(waves);
+        game.step_frames(2);
+
+        let ships = squad_ships(&mut game);
+        assert_eq!(game.position(ships[1]).x, 0.0);
+    }
+
+    #[test]
+    fn shot_down_ships_leave_the_squad() {
+        let mut game = TestGame::with_waves(squad_of_three());
+        game.step_frames(50);
+        let ship = squad_ships(&mut game)[0];
+
+        shoot_down(&mut game, ship);
+
+        assert!(!game.exists(ship));
+        assert_eq!(squad_ships(&mut game).len(), 2);
+        assert_eq!(game.player_state().score, 35);
+    }
+}
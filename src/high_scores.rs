@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Action, ActionState},
+    replay::ReplayPlayer,
+    rng::GameRng,
+    storage, team_score, AppState, Game, Player,
+};
+
+/// Entries kept in the table.
+const MAX_ENTRIES: usize = 10;
+const MAX_NAME_LEN: usize = 12;
+
+const HIGH_SCORES_FILE: &str = "high_scores";
+
+/// Keeps the best runs in `high_scores.ron` (native) or the `localStorage` of the browser (wasm).
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            // Replays show the run of someone else, who may already be in the table.
+            .add_system(
+                check_high_score
+                    .run_if(not(resource_exists::<ReplayPlayer>()))
+                    .in_schedule(OnEnter(AppState::GameOver)),
+            )
+            .add_system(
+                check_high_score
+                    .run_if(not(resource_exists::<ReplayPlayer>()))
+                    .in_schedule(OnEnter(AppState::GameWon)),
+            )
+            .add_system(
+                enter_name
+                    .run_if(resource_exists::<NameEntry>())
+                    .in_set(OnUpdate(AppState::GameOver)),
+            )
+            .add_system(
+                enter_name
+                    .run_if(resource_exists::<NameEntry>())
+                    .in_set(OnUpdate(AppState::GameWon)),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u32,
+    pub level: u32,
+    pub seed: u64,
+    /// Day of the run, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// Best runs first.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    /// Where a score would end up in the table, `None` if it's not good enough.
+    pub fn rank(&self, score: u32) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+
+        (rank < MAX_ENTRIES).then_some(rank)
+    }
+
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let rank = self.rank(entry.score)?;
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    fn load() -> Self {
+        storage::load(HIGH_SCORES_FILE)
+            .map(|ron| Self::from_ron(&ron))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        storage::save(HIGH_SCORES_FILE, &self.to_ron());
+    }
+
+    fn from_ron(ron: &str) -> Self {
+        ron::from_str(ron).unwrap_or_else(|err| {
+            warn!("Ignoring broken high scores: {err}");
+            Self::default()
+        })
+    }
+
+    fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn days_since_epoch() -> u64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_secs() / 86_400
+}
+
+#[cfg(target_arch = "wasm32")]
+fn days_since_epoch() -> u64 {
+    (js_sys::Date::now() / 86_400_000.0) as u64
+}
+
+/// Formats days since 1970-01-01 as `YYYY-MM-DD`.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn format_date(days: u64) -> String {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// A run that made it into the table, waiting for its name.
+/// Restarting is blocked while it exists, so `R` can be typed.
+#[derive(Resource)]
+pub struct NameEntry {
+    name: String,
+    score: u32,
+    level: u32,
+    seed: u64,
+}
+
+#[derive(Component)]
+struct NameEntryText;
+
+fn check_high_score(
+    mut commands: Commands,
+    game: Res<Game>,
+    player_query: Query<&Player>,
+    state: Res<State<AppState>>,
+    rng: Res<GameRng>,
+    high_scores: Res<HighScores>,
+    asset_server: Res<AssetServer>,
+) {
+    let score = if state.0 == AppState::GameWon {
+        game.final_score(&player_query)
+    } else {
+        team_score(&player_query)
+    };
+
+    if score == 0 || high_scores.rank(score).is_none() {
+        return;
+    }
+
+    commands.insert_resource(NameEntry {
+        name: String::new(),
+        score,
+        level: game.level,
+        seed: rng.seed,
+    });
+
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/impact.ttf"),
+        font_size: 39.0,
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("NEW HIGH SCORE! TYPE YOUR NAME: ", text_style.clone()),
+            TextSection::new("_", text_style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(12.0),
+                bottom: Val::Px(60.0),
+                ..default()
+            },
+            ..default()
+        }),
+        NameEntryText,
+    ));
+}
+
+fn enter_name(
+    mut commands: Commands,
+    mut entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    mut ev_character: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
+    mut text_query: Query<(Entity, &mut Text), With<NameEntryText>>,
+) {
+    let (text_entity, mut text) = text_query.single_mut();
+
+    for ev in ev_character.iter() {
+        if (ev.char.is_ascii_alphanumeric() || ev.char == ' ') && entry.name.len() < MAX_NAME_LEN {
+            entry.name.push(ev.char.to_ascii_uppercase());
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        entry.name.pop();
+    }
+
+    if actions.just_pressed(Action::Confirm) {
+        let name = match entry.name.trim() {
+            "" => "ANONYMOUS".to_string(),
+            name => name.to_string(),
+        };
+
+        high_scores.insert(HighScoreEntry {
+            name,
+            score: entry.score,
+            level: entry.level,
+            seed: entry.seed,
+            date: format_date(days_since_epoch()),
+        });
+        high_scores.save();
+
+        commands.remove_resource::<NameEntry>();
+        commands.entity(text_entity).despawn();
+    } else {
+        text.sections[1].value = format!("{}_", entry.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, HighScoreEntry, HighScores, MAX_ENTRIES};
+
+    fn entry(name: &str, score: u32) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_string(),
+            score,
+            level: 1,
+            seed: 0,
+            date: "2023-04-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_are_sorted_by_score() {
+        let mut high_scores = HighScores::default();
+
+        assert_eq!(high_scores.insert(entry("A", 100)), Some(0));
+        assert_eq!(high_scores.insert(entry("B", 300)), Some(0));
+        assert_eq!(high_scores.insert(entry("C", 200)), Some(1));
+        // Ties go below the ones that were there first.
+        assert_eq!(high_scores.insert(entry("D", 200)), Some(2));
+
+        let names: Vec<_> = high_scores
+            .entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, ["B", "C", "D", "A"]);
+    }
+
+    #[test]
+    fn only_the_best_entries_are_kept() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=MAX_ENTRIES as u32 {
+            high_scores.insert(entry("A", score * 10));
+        }
+
+        assert_eq!(high_scores.rank(5), None);
+        assert_eq!(high_scores.insert(entry("B", 15)), Some(MAX_ENTRIES - 1));
+        assert_eq!(high_scores.entries.len(), MAX_ENTRIES);
+        assert_eq!(high_scores.entries.last().unwrap().score, 15);
+    }
+
+    #[test]
+    fn high_scores_survive_a_round_trip_through_ron() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("A", 100));
+
+        let loaded = HighScores::from_ron(&high_scores.to_ron());
+
+        assert_eq!(loaded.entries, high_scores.entries);
+    }
+
+    #[test]
+    fn dates_are_formatted_as_iso_days() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(11_016), "2000-02-29");
+        assert_eq!(format_date(19_447), "2023-03-31");
+    }
+}
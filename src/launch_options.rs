@@ -0,0 +1,21 @@
+/// Reads `--<name> <value>` from the command line.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn launch_option(name: &str) -> Option<String> {
+    let flag = format!("--{name}");
+    let mut args = std::env::args().skip_while(|arg| *arg != flag);
+    args.next()?;
+    args.next()
+}
+
+/// Reads `?<name>=<value>` from the URL of the page.
+#[cfg(target_arch = "wasm32")]
+pub fn launch_option(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let prefix = format!("{name}=");
+
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(prefix.as_str()))
+        .map(String::from)
+}
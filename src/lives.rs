@@ -0,0 +1,221 @@
+//! The player has a few lives. Losing one blows up the ship, which comes back at the bottom
+//! after a moment and can't be hit for a few seconds, blinking until it can.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    check_game_over,
+    collision::Collider,
+    coop::{start_position, GameMode},
+    playfield::PLAYFIELD,
+    simulation::{Interpolated, TickSet},
+    AnimationIndices, AnimationTimer, Layers, MyAssets, Player, PLAYER_HEALTH,
+};
+
+pub const PLAYER_LIVES: u32 = 3;
+const RESPAWN_DELAY: Duration = Duration::from_millis(1500);
+const INVINCIBILITY: Duration = Duration::from_secs(3);
+const BLINK_INTERVAL: f32 = 0.1;
+
+pub struct LivesPlugin;
+
+impl Plugin for LivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                player_death.before(check_game_over),
+                respawn_player,
+                wear_off_invincibility,
+            )
+                .in_set(TickSet::Gameplay)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// The player's ship blew up and comes back once the timer finishes.
+#[derive(Component)]
+pub struct Respawning(Timer);
+
+/// The player's ship just came back and can't be hit until the timer finishes.
+#[derive(Component)]
+pub struct Invincible(Timer);
+
+/// Filter for a player that can be hit.
+pub type Vulnerable = (Without<Respawning>, Without<Invincible>);
+
+fn player_death(
+    mut commands: Commands,
+    mut player_query: Query<
+        (Entity, &mut Player, &Transform, &mut Visibility),
+        Without<Respawning>,
+    >,
+    my_assets: Res<MyAssets>,
+) {
+    for (player_entity, mut player, transform, mut visibility) in &mut player_query {
+        if player.health > 0 {
+            continue;
+        }
+
+        player.lives = player.lives.saturating_sub(1);
+        *visibility = Visibility::Hidden;
+        commands
+            .entity(player_entity)
+            .insert(Respawning(Timer::new(RESPAWN_DELAY, TimerMode::Once)));
+
+        let animation_indices = AnimationIndices { first: 0, last: 11 };
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: my_assets.explosion.clone(),
+                sprite: TextureAtlasSprite::new(animation_indices.first),
+                transform: Transform::from_xyz(
+                    transform.translation.x,
+                    transform.translation.y,
+                    Layers::Actors.order_nr(),
+                ),
+                ..default()
+            },
+            animation_indices,
+            AnimationTimer(Timer::from_seconds(0.019, TimerMode::Repeating)),
+        ));
+    }
+}
+
+/// Players without lives left stay out, the game is over once all of them are, see `check_game_over`.
+fn respawn_player(
+    fixed_time: Res<FixedTime>,
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mut player_query: Query<(
+        Entity,
+        &mut Player,
+        &mut Respawning,
+        &mut Transform,
+        &mut Interpolated,
+        &mut Visibility,
+        &Collider,
+    )>,
+) {
+    for (
+        entity,
+        mut player,
+        mut respawning,
+        mut transform,
+        mut interpolated,
+        mut visibility,
+        collider,
+    ) in &mut player_query
+    {
+        if player.lives == 0 || !respawning.0.tick(fixed_time.period).finished() {
+            continue;
+        }
+
+        player.health = PLAYER_HEALTH;
+        transform.translation = spawn_point(player.index, mode.players(), collider);
+        *interpolated = Interpolated::new(transform.translation);
+        *visibility = Visibility::Inherited;
+        commands
+            .entity(entity)
+            .remove::<Respawning>()
+            .insert(Invincible(Timer::new(INVINCIBILITY, TimerMode::Once)));
+    }
+}
+
+/// At the bottom of the playfield, below where the player started.
+pub fn spawn_point(index: usize, players: usize, collider: &Collider) -> Vec3 {
+    Vec3::new(
+        start_position(index, players).x,
+        -PLAYFIELD.y / 2.0 + collider.size.y / 2.0,
+        Layers::Actors.order_nr(),
+    )
+}
+
+fn wear_off_invincibility(
+    fixed_time: Res<FixedTime>,
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Invincible, &mut Visibility)>,
+) {
+    for (entity, mut invincible, mut visibility) in &mut player_query {
+        invincible.0.tick(fixed_time.period);
+
+        let blink = (invincible.0.elapsed_secs() / BLINK_INTERVAL) as u32 & 1 == 0;
+        *visibility = if blink && !invincible.0.finished() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        if invincible.0.finished() {
+            commands.entity(entity).remove::<Invincible>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{Invincible, Respawning, PLAYER_LIVES};
+    use crate::{simulation::TICK_RATE, test_harness::TestGame, AppState, PLAYER_HEALTH};
+
+    const SECOND: u32 = TICK_RATE as u32;
+
+    #[test]
+    fn dying_costs_a_life_and_respawns_the_player() {
+        let mut game = TestGame::new();
+        let player = game.player();
+        game.player_state_mut().health = 0;
+
+        game.step();
+
+        assert_eq!(game.player_state().lives, PLAYER_LIVES - 1);
+        assert_eq!(game.state(), AppState::InGame);
+        assert!(game.app.world.get::<Respawning>(player).is_some());
+
+        game.step_frames(2 * SECOND);
+
+        assert_eq!(game.player_state().health, PLAYER_HEALTH);
+        assert!(game.app.world.get::<Respawning>(player).is_none());
+        assert!(game.app.world.get::<Invincible>(player).is_some());
+        assert!(game.position(player).y < 0.0);
+    }
+
+    #[test]
+    fn invincible_player_is_not_hit() {
+        let mut game = TestGame::new();
+        let player = game.player();
+        game.player_state_mut().health = 0;
+        game.step_frames(2 * SECOND);
+
+        let enemy = game.spawn_enemy("trespasser", game.position(player).truncate());
+        game.step();
+
+        assert!(game.exists(enemy));
+        assert_eq!(game.player_state().health, PLAYER_HEALTH);
+
+        game.step_frames(3 * SECOND);
+        let enemy = game.spawn_enemy("trespasser", game.position(player).truncate());
+        game.step();
+
+        assert!(!game.exists(enemy));
+        assert!(game.player_state().health < PLAYER_HEALTH);
+    }
+
+    #[test]
+    fn game_is_over_without_lives_left() {
+        let mut game = TestGame::new();
+        let player = game.player();
+        game.player_state_mut().lives = 1;
+        game.player_state_mut().health = 0;
+
+        game.step_frames(2);
+
+        assert_eq!(game.state(), AppState::GameOver);
+        assert_eq!(
+            game.app.world.get::<Visibility>(player),
+            Some(&Visibility::Hidden)
+        );
+    }
+}
@@ -1,17 +1,34 @@
 //! Renders a 2D scene containing a single, moving sprite.
 
-use bevy::{
-    prelude::*,
-    window::{PresentMode, PrimaryWindow},
-};
+use bevy::{asset::LoadState, prelude::*, window::PresentMode};
 use bevy_asset_loader::prelude::{AssetCollection, LoadingState, LoadingStateAppExt};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use rand::Rng;
 
 pub mod backdrop;
+pub mod boss;
+pub mod collision;
+pub mod controls;
+pub mod coop;
 pub mod enemy_spawning;
+pub mod formation;
+pub mod high_scores;
+pub mod launch_options;
+pub mod lives;
+pub mod menu;
+pub mod movement;
+pub mod netplay;
 pub mod player_control;
+pub mod playfield;
+pub mod pointer;
+pub mod replay;
+pub mod rng;
+pub mod ron_asset;
+pub mod settings;
 pub mod shooting;
+pub mod simulation;
+pub mod storage;
+#[cfg(test)]
+mod test_harness;
 pub mod ui;
 
 const EARTH_HEALTH: u32 = 5000;
@@ -39,66 +56,117 @@ impl Layers {
     }
 }
 
-const ORIGINAL_TARGET_FPS: f32 = 40.0;
-
-// TODO: Ensure resizing window doesn't break things like enemy spawning/despawning, player movement or earth health.
-// Moving Player certainly needs some tweaking since he can get stuck if the window is resized into the player.
-
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "SpaceShipProject Rust Edition!".into(),
-                present_mode: PresentMode::AutoVsync,
-                resolution: (1120., 605.).into(),
-                // Tell wasm to use a specific canvas.
-                canvas: Some(String::from("#mainScreen")),
-                // Tells wasm NOT to resize the window according to the available canvas.
-                fit_canvas_to_parent: false,
-                // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
-                prevent_default_event_handling: false,
-                ..default()
-            }),
-            ..default()
-        }))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "SpaceShipProject Rust Edition!".into(),
+                        present_mode: PresentMode::AutoVsync,
+                        resolution: (PLAYFIELD.x, PLAYFIELD.y).into(),
+                        // Tell wasm to use a specific canvas.
+                        canvas: Some(String::from("#mainScreen")),
+                        // Tells wasm NOT to resize the window according to the available canvas.
+                        fit_canvas_to_parent: false,
+                        // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
+                        prevent_default_event_handling: false,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    // Hot-reload data files like weapons while developing.
+                    watch_for_changes: cfg!(all(debug_assertions, not(target_arch = "wasm32"))),
+                    ..default()
+                }),
+        )
+        .insert_resource(GameRng::from_launch_options())
+        .insert_resource(controls::KeyBindings::load())
+        .insert_resource(settings::Settings::load())
+        .add_plugin(replay::ReplayPlugin::from_launch_options())
+        .add_plugin(netplay::NetplayPlugin::from_launch_options())
         .add_state::<AppState>()
-        .add_loading_state(LoadingState::new(AppState::Loading).continue_to_state(AppState::InGame))
-        .add_collection_to_loading_state::<_, MyAssets>(AppState::Loading)
-        .insert_resource(Game {
-            health: PLAYER_HEALTH,
-            earth_health: EARTH_HEALTH,
-            score: 0,
-            level: 1,
-        })
-        // TODO: Find a way so that it doesn't run when unpausing the game
-        .add_event::<LevelUpEvent>()
-        .add_system(setup.in_schedule(OnExit(AppState::Loading)))
-        .add_systems(
-            (
-                despawn_enemies,
-                enemy_collision,
-                animate_sprite,
-                check_game_over,
-                check_game_won.after(check_game_over),
-                check_game_paused,
-                check_player_level_up,
-            )
-                .in_set(OnUpdate(AppState::InGame)),
+        .add_loading_state(
+            LoadingState::new(AppState::Loading).continue_to_state(AppState::LoadingDependencies),
         )
-        .add_system(check_game_unpaused.in_set(OnUpdate(AppState::Paused)))
-        .add_plugin(backdrop::BackdropPlugin)
-        .add_plugin(enemy_spawning::EnemySpawningPlugin)
-        .add_plugin(player_control::PlayerControlPlugin)
-        .add_plugin(shooting::ShootingPlugin)
+        .add_collection_to_loading_state::<_, MyAssets>(AppState::Loading)
+        .add_system(check_dependencies_loaded.in_set(OnUpdate(AppState::LoadingDependencies)))
+        .add_plugin(playfield::PlayfieldPlugin)
+        .add_plugin(GamePlugin)
         .add_plugin(ui::UiOverlayPlugin)
+        .add_plugin(menu::MenuPlugin)
+        .add_plugin(pointer::PointerPlugin)
+        .add_plugin(high_scores::HighScorePlugin)
         //.add_plugin(WorldInspectorPlugin::new())
         .run();
 }
 
+/// The gameplay itself, without windowing, rendering or asset loading.
+/// This way it also runs headless, see `test_harness`.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Game::new())
+            .init_resource::<Settings>()
+            // TODO: Find a way so that it doesn't run when unpausing the game
+            .add_event::<LevelUpEvent>()
+            .add_event::<EnemyDestroyedEvent>()
+            .add_systems(
+                (
+                    despawn_enemies,
+                    enemy_collision.after(CollisionGridSystem),
+                    check_game_over,
+                    check_game_won.after(check_game_over),
+                    check_player_level_up,
+                )
+                    .in_set(TickSet::Gameplay)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    animate_sprite,
+                    check_game_paused.run_if(not(netplay::online)),
+                )
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(check_game_unpaused.in_set(OnUpdate(AppState::Paused)))
+            .add_system(
+                check_game_restarted
+                    .run_if(not(resource_exists::<NameEntry>()))
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .in_set(OnUpdate(AppState::GameOver)),
+            )
+            .add_system(
+                check_game_restarted
+                    .run_if(not(resource_exists::<NameEntry>()))
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .in_set(OnUpdate(AppState::GameWon)),
+            )
+            .add_system(reset_game.in_schedule(OnExit(AppState::GameOver)))
+            .add_system(reset_game.in_schedule(OnExit(AppState::GameWon)))
+            .add_plugin(controls::ControlsPlugin)
+            .add_plugin(collision::CollisionPlugin)
+            .add_plugin(coop::CoopPlugin)
+            .add_plugin(lives::LivesPlugin)
+            .add_plugin(simulation::SimulationPlugin)
+            .add_plugin(backdrop::BackdropPlugin)
+            .add_plugin(boss::BossPlugin)
+            .add_plugin(enemy_spawning::EnemySpawningPlugin)
+            .add_plugin(formation::FormationPlugin)
+            .add_plugin(movement::MovementPlugin)
+            .add_plugin(player_control::PlayerControlPlugin)
+            .add_plugin(shooting::ShootingPlugin);
+    }
+}
+
 #[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default)]
 enum AppState {
     #[default]
     Loading,
+    LoadingDependencies,
+    MainMenu,
     InGame,
     Paused,
     GameOver,
@@ -109,14 +177,6 @@ enum AppState {
 pub struct MyAssets {
     #[asset(path = "player.png")]
     player: Handle<Image>,
-    #[asset(path = "ships/BigShip.png")]
-    big_ship: Handle<Image>,
-    #[asset(path = "ships/darkLord.png")]
-    dark_lord: Handle<Image>,
-    #[asset(path = "ships/spaceCrusader.png")]
-    space_crusader: Handle<Image>,
-    #[asset(path = "ships/trespasser.png")]
-    trespasser: Handle<Image>,
     #[asset(path = "backdrop/star.png")]
     star: Handle<Image>,
     #[asset(path = "backdrop/star1.png")]
@@ -155,16 +215,12 @@ pub struct MyAssets {
     ))]
     #[asset(path = "explosion.png")]
     explosion: Handle<TextureAtlas>,
-    #[asset(path = "projectiles/blaster.png")]
-    blaster: Handle<Image>,
-    #[asset(path = "projectiles/grim.png")]
-    grim: Handle<Image>,
-    #[asset(path = "projectiles/hammer.png")]
-    hammer: Handle<Image>,
-    #[asset(path = "projectiles/ratata.png")]
-    ratata: Handle<Image>,
-    #[asset(path = "projectiles/stomp.png")]
-    stomp: Handle<Image>,
+    #[asset(path = "data/arsenal.weapons.ron")]
+    weapons: Handle<WeaponCatalog>,
+    #[asset(path = "data/fleet.enemies.ron")]
+    enemies: Handle<EnemyArchetypes>,
+    #[asset(path = "data/campaign.waves.ron")]
+    waves: Handle<WaveScript>,
     #[asset(texture_atlas(
         tile_size_x = 48.,
         tile_size_y = 46.,
@@ -205,25 +261,29 @@ pub struct MyAssets {
 
 #[derive(Component)]
 struct Player {
+    /// 0 for the first player, 1 for the second one in co-op.
+    pub index: usize,
     pub speed: f32,
     pub current_weapon_index: usize,
     pub weapons: Vec<Weapon>,
+    pub health: u32,
+    pub lives: u32,
+    pub score: u32,
+    /// Unlocks one more weapon with every level.
+    pub level: u32,
 }
 
 impl Player {
-    pub fn new() -> Self {
-        let weapons = vec![
-            Weapon::stomp(true),
-            Weapon::blaster(true),
-            Weapon::grim(true),
-            Weapon::hammer(true),
-            Weapon::ratata(true),
-        ];
-
+    pub fn new(index: usize, weapons: Vec<Weapon>) -> Self {
         Self {
+            index,
             speed: 5.618,
             weapons: weapons,
             current_weapon_index: 0,
+            health: PLAYER_HEALTH,
+            lives: PLAYER_LIVES,
+            score: 0,
+            level: 1,
         }
     }
 
@@ -232,17 +292,8 @@ impl Player {
     }
 }
 
-#[derive(Clone)]
-enum ShipType {
-    BigShip,
-    DarkLord,
-    SpeedCrusader,
-    Trespasser,
-}
-
 #[derive(Component, Clone)]
-struct Enemy {
-    pub ship_type: ShipType,
+pub struct Enemy {
     pub health: u32,
     pub collision_damage: u32,
     pub bounty: u32,
@@ -250,117 +301,64 @@ struct Enemy {
     pub weapon: Option<Weapon>,
 }
 
-impl Enemy {
-    pub fn random() -> Self {
-        let next_enemy_type = rand::thread_rng().gen_range(0..100);
-
-        if next_enemy_type < 45 {
-            Self::trespasser()
-        } else if next_enemy_type < 70 {
-            Self::space_crusader()
-        } else if next_enemy_type < 95 {
-            Self::big_ship()
-        } else {
-            Self::dark_lord()
-        }
-    }
-
-    pub fn big_ship() -> Self {
-        let speed = rand::thread_rng().gen_range(0.971 - 0.03..0.971 + 0.034);
-
-        Self {
-            ship_type: ShipType::BigShip,
-            health: 100,
-            speed: speed,
-            collision_damage: 35,
-            bounty: 120,
-            weapon: Some(Weapon::stomp(false)),
-        }
-    }
-
-    pub fn dark_lord() -> Self {
-        let speed = rand::thread_rng().gen_range(0.63 - 0.03..0.63 + 0.06);
-
-        Self {
-            ship_type: ShipType::DarkLord,
-            health: 250,
-            speed: speed,
-            collision_damage: 75,
-            bounty: 250,
-            weapon: Some(Weapon::grim(false)),
-        }
-    }
-
-    pub fn space_crusader() -> Self {
-        let speed = rand::thread_rng().gen_range(1.001 - 0.04..1.001 + 0.04);
-
-        Self {
-            ship_type: ShipType::SpeedCrusader,
-            health: 80,
-            speed: speed,
-            collision_damage: 55,
-            bounty: 180,
-            weapon: Some(Weapon::hammer(false)),
-        }
-    }
-
-    pub fn trespasser() -> Self {
-        let speed = rand::thread_rng().gen_range(1.53 - 0.09..1.53 + 0.08);
+/// What all players share, everything else is on their `Player`.
+#[derive(Resource)]
+struct Game {
+    pub earth_health: u32,
+    /// Level of the waves, the highest one of the players.
+    pub level: u32,
+}
 
+impl Game {
+    pub fn new() -> Self {
         Self {
-            ship_type: ShipType::Trespasser,
-            health: 30,
-            speed: speed,
-            collision_damage: 13,
-            bounty: 35,
-            weapon: None,
+            earth_health: EARTH_HEALTH,
+            level: 1,
         }
     }
 
-    pub fn image(&self, assets: Res<MyAssets>) -> Handle<Image> {
-        match self.ship_type {
-            ShipType::BigShip => assets.big_ship.clone(),
-            ShipType::DarkLord => assets.dark_lord.clone(),
-            ShipType::SpeedCrusader => assets.space_crusader.clone(),
-            ShipType::Trespasser => assets.trespasser.clone(),
-        }
+    /// What's left of earth and the players counts too once the game is won.
+    pub fn final_score<'a>(&self, players: impl IntoIterator<Item = &'a Player>) -> u32 {
+        self.earth_health
+            + players
+                .into_iter()
+                .map(|player| player.score + player.health)
+                .sum::<u32>()
     }
 }
 
-// TODO: Move Health and Score to Player.
-#[derive(Resource)]
-struct Game {
-    pub health: u32,
-    pub earth_health: u32,
-    pub score: u32,
-    pub level: u32,
+/// The players play together, so they win and get on the high score list together.
+fn team_score<'a>(players: impl IntoIterator<Item = &'a Player>) -> u32 {
+    players.into_iter().map(|player| player.score).sum()
 }
 
-fn setup(mut commands: Commands, my_assets: Res<MyAssets>) {
-    commands.spawn(Camera2dBundle::default());
-    commands.spawn((
-        SpriteBundle {
-            texture: my_assets.player.clone(),
-            transform: Transform::from_xyz(0., 0., Layers::Actors.order_nr()),
-            ..default()
-        },
-        Player::new(),
-    ));
+/// Data files reference sprites by path, those get loaded as dependencies after `MyAssets`.
+fn check_dependencies_loaded(
+    asset_server: Res<AssetServer>,
+    my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+    enemy_archetypes: Res<Assets<EnemyArchetypes>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let weapons = weapon_catalogs.get(&my_assets.weapons).unwrap();
+    let enemies = enemy_archetypes.get(&my_assets.enemies).unwrap();
+    let handles = weapons
+        .projectile_images()
+        .chain(enemies.images())
+        .map(|handle| handle.id());
+
+    if asset_server.get_group_load_state(handles) == LoadState::Loaded {
+        next_state.set(AppState::MainMenu);
+    }
 }
 
 fn despawn_enemies(
     mut commands: Commands,
     mut game: ResMut<Game>,
-    mut sprite_position: Query<(Entity, &mut Enemy, &mut Transform, &Handle<Image>)>,
-    assets: Res<Assets<Image>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut sprite_position: Query<(Entity, &mut Enemy, &mut Transform, &Collider)>,
 ) {
-    let window = window_query.single();
-
-    for (enemy_entity, enemy, transform, img_handle) in &mut sprite_position {
-        let enemy_size = assets.get(img_handle).unwrap().size();
-
-        if enemy_past_bottom(transform.translation.y, window, enemy_size) {
+    for (enemy_entity, enemy, transform, collider) in &mut sprite_position {
+        if enemy_past_bottom(transform.translation.y, collider.size) {
             game.earth_health = if let Some(i) = game.earth_health.checked_sub(enemy.bounty) {
                 i
             } else {
@@ -372,8 +370,8 @@ fn despawn_enemies(
     }
 }
 
-fn enemy_past_bottom(y: f32, window: &Window, enemy_size: Vec2) -> bool {
-    let min_y = -(window.height() / 2.) - (enemy_size.y / 2.);
+fn enemy_past_bottom(y: f32, enemy_size: Vec2) -> bool {
+    let min_y = -(PLAYFIELD.y / 2.) - (enemy_size.y / 2.);
     y < min_y
 }
 
@@ -408,39 +406,67 @@ fn animate_sprite(
     }
 }
 
-use bevy::sprite::collide_aabb::collide;
-use shooting::Weapon;
+use boss::Boss;
+use collision::{Collider, CollisionGrid, CollisionGridSystem};
+use controls::{Action, ActionState};
+use enemy_spawning::{EnemyArchetypes, WaveScript};
+use formation::Squad;
+use high_scores::NameEntry;
+use lives::{Invincible, Respawning, Vulnerable, PLAYER_LIVES};
+use netplay::NetSession;
+use playfield::PLAYFIELD;
+use rand::Rng;
+use rng::GameRng;
+use settings::Settings;
+use shooting::{Projectile, Weapon, WeaponCatalog};
+use simulation::{Interpolated, TickSet};
 
+#[allow(clippy::too_many_arguments)]
 fn enemy_collision(
     mut commands: Commands,
-    mut game: ResMut<Game>,
-    assets: Res<Assets<Image>>,
-    player_query: Query<(&mut Player, &mut Transform, &mut Handle<Image>), Without<Enemy>>,
-    enemies_query: Query<(Entity, &mut Enemy, &mut Transform, &mut Handle<Image>), Without<Player>>,
+    grid: Res<CollisionGrid>,
+    mut player_query: Query<(Entity, &mut Player, &mut Transform, &Collider), Vulnerable>,
+    enemies_query: Query<(Entity, &mut Enemy, &mut Transform, &Collider), Without<Player>>,
+    boss_query: Query<(), With<Boss>>,
     my_assets: Res<MyAssets>,
+    mut destroyed: Local<Vec<Entity>>,
+    mut ev_enemydestroyed: EventWriter<EnemyDestroyedEvent>,
 ) {
-    let player = player_query.single();
-    let player_size = assets.get(player.2).unwrap().size();
-
-    for (enemy_entity, enemy, pos, img) in &enemies_query {
-        let enemy_size = assets.get(img).unwrap().size();
+    // An enemy crashing into two players at once only gets destroyed once.
+    destroyed.clear();
+
+    for (player_entity, mut player, mut player_pos, player_collider) in &mut player_query {
+        for candidate in grid.candidates(player_pos.translation, player_collider) {
+            if destroyed.contains(&candidate) {
+                continue;
+            }
+            let Ok((enemy_entity, enemy, pos, collider)) = enemies_query.get(candidate) else {
+                continue;
+            };
+            if !collider.collides(pos.translation, player_collider, player_pos.translation) {
+                continue;
+            }
 
-        if collide(
-            pos.translation,
-            enemy_size,
-            player.1.translation,
-            player_size,
-        )
-        .is_some()
-        {
-            game.health = if let Some(i) = game.health.checked_sub(enemy.collision_damage) {
+            player.health = if let Some(i) = player.health.checked_sub(enemy.collision_damage) {
                 i
             } else {
                 0
             };
 
-            game.score += enemy.bounty;
+            // Bosses can't be rammed, they shove the ship out of the way instead.
+            if boss_query.contains(enemy_entity) {
+                player_pos.translation.y =
+                    pos.translation.y - (collider.hitbox.y + player_collider.hitbox.y) / 2.0;
+                continue;
+            }
+
+            player.score += enemy.bounty;
             commands.entity(enemy_entity).despawn();
+            destroyed.push(enemy_entity);
+            ev_enemydestroyed.send(EnemyDestroyedEvent {
+                enemy: enemy_entity,
+                player: Some(player_entity),
+            });
 
             let animation_indices = AnimationIndices { first: 0, last: 11 };
             commands.spawn((
@@ -461,43 +487,272 @@ fn enemy_collision(
     }
 }
 
-fn check_game_paused(
-    mut next_state: ResMut<NextState<AppState>>,
-    keyboard_input: Res<Input<KeyCode>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+fn check_game_paused(mut next_state: ResMut<NextState<AppState>>, actions: Res<ActionState>) {
+    if actions.just_pressed(Action::Pause) {
         next_state.set(AppState::Paused);
     }
 }
 
-fn check_game_unpaused(
+fn check_game_unpaused(mut next_state: ResMut<NextState<AppState>>, actions: Res<ActionState>) {
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(AppState::InGame);
+    }
+}
+
+fn check_game_restarted(
     mut next_state: ResMut<NextState<AppState>>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
+    mut rng: ResMut<GameRng>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        next_state.set(AppState::InGame);
+    let state = if actions.just_pressed(Action::Restart) {
+        AppState::InGame
+    } else if actions.just_pressed(Action::Back) {
+        AppState::MainMenu
+    } else {
+        return;
+    };
+    next_state.set(state);
+
+    // Every game gets its own seed. Deriving it from the last one keeps replays deterministic.
+    let seed = rng.gameplay.gen();
+    *rng = GameRng::new(seed);
+}
+
+/// Entities that only live during a game: ships, shots, squads and effects.
+type Leftovers = Or<(
+    With<Enemy>,
+    With<Projectile>,
+    With<AnimationIndices>,
+    With<Squad>,
+)>;
+
+/// Clears everything left over from the last game, whether it's restarted right away or not.
+fn reset_game(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    mut player_query: Query<(
+        Entity,
+        &mut Player,
+        &mut Transform,
+        &mut Interpolated,
+        &mut Visibility,
+    )>,
+    leftovers_query: Query<Entity, Leftovers>,
+    my_assets: Res<MyAssets>,
+    weapon_catalogs: Res<Assets<WeaponCatalog>>,
+) {
+    *game = Game::new();
+
+    let weapons = weapon_catalogs.get(&my_assets.weapons).unwrap();
+    let players = player_query.iter().len();
+    for (entity, mut player, mut transform, mut interpolated, mut visibility) in &mut player_query {
+        *player = Player::new(player.index, weapons.player_weapons());
+        transform.translation = coop::start_position(player.index, players);
+        *interpolated = Interpolated::new(transform.translation);
+        *visibility = Visibility::Inherited;
+        commands
+            .entity(entity)
+            .remove::<Respawning>()
+            .remove::<Invincible>();
+    }
+
+    for entity in &leftovers_query {
+        commands.entity(entity).despawn();
     }
 }
 
-fn check_game_over(game: Res<Game>, mut next_state: ResMut<NextState<AppState>>) {
-    if game.health <= 0 || game.earth_health <= 0 {
+/// The game goes on as long as one of the players has lives left.
+fn check_game_over(
+    game: Res<Game>,
+    player_query: Query<&Player>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if player_query.iter().all(|player| player.lives == 0) || game.earth_health == 0 {
         next_state.set(AppState::GameOver);
     }
 }
 
-fn check_game_won(game: Res<Game>, mut next_state: ResMut<NextState<AppState>>) {
-    if game.score >= 8000 {
+fn check_game_won(player_query: Query<&Player>, mut next_state: ResMut<NextState<AppState>>) {
+    if team_score(&player_query) >= 8000 {
         next_state.set(AppState::GameWon);
     }
 }
 
-struct LevelUpEvent;
+/// Sent with the level a player just reached.
+struct LevelUpEvent {
+    pub level: u32,
+}
+
+/// Sent for every enemy shot down or rammed, with the player who did it if known.
+struct EnemyDestroyedEvent {
+    pub enemy: Entity,
+    pub player: Option<Entity>,
+}
 
-fn check_player_level_up(mut game: ResMut<Game>, mut ev_levelup: EventWriter<LevelUpEvent>) {
-    if game.score > game.level * 1000 {
-        if game.level < 5 {
-            game.level += 1;
-            ev_levelup.send(LevelUpEvent)
+fn check_player_level_up(
+    mut game: ResMut<Game>,
+    mut player_query: Query<&mut Player>,
+    mut ev_levelup: EventWriter<LevelUpEvent>,
+) {
+    for mut player in &mut player_query {
+        if player.score > player.level * 1000 && player.level < 5 {
+            player.level += 1;
+            ev_levelup.send(LevelUpEvent {
+                level: player.level,
+            });
         }
+        game.level = game.level.max(player.level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, prelude::*};
+
+    use crate::{
+        coop::GameMode, high_scores::HighScores, lives::PLAYER_LIVES, menu, test_harness::TestGame,
+        AppState, LevelUpEvent, Player, EARTH_HEALTH, PLAYER_HEALTH,
+    };
+
+    #[test]
+    fn enemy_collision_damages_player_and_destroys_enemy() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("trespasser", Vec2::ZERO);
+
+        game.step();
+
+        assert!(!game.exists(enemy));
+        assert_eq!(game.player_state().health, PLAYER_HEALTH - 13);
+        assert_eq!(game.player_state().score, 35);
+    }
+
+    #[test]
+    fn escaped_enemy_damages_earth() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("big_ship", Vec2::new(0.0, -400.0));
+
+        game.step();
+
+        assert!(!game.exists(enemy));
+        assert_eq!(game.game().earth_health, EARTH_HEALTH - 120);
+    }
+
+    #[test]
+    fn game_over_once_player_is_out_of_lives() {
+        let mut game = TestGame::new();
+        game.player_state_mut().lives = 1;
+        game.player_state_mut().health = 0;
+
+        game.step_frames(2);
+
+        assert_eq!(game.state(), AppState::GameOver);
+    }
+
+    #[test]
+    fn game_goes_on_while_a_player_has_lives() {
+        let mut game = TestGame::with_mode(GameMode::Coop);
+        let [first, second] = game.players()[..] else {
+            panic!("Co-op needs two players");
+        };
+        let mut player = game.app.world.get_mut::<Player>(first).unwrap();
+        player.lives = 1;
+        player.health = 0;
+
+        game.step_frames(2);
+
+        assert_eq!(game.state(), AppState::InGame);
+
+        let mut player = game.app.world.get_mut::<Player>(second).unwrap();
+        player.lives = 1;
+        player.health = 0;
+
+        game.step_frames(2);
+
+        assert_eq!(game.state(), AppState::GameOver);
+    }
+
+    #[test]
+    fn game_over_once_earth_is_destroyed() {
+        let mut game = TestGame::new();
+        game.game_mut().earth_health = 0;
+
+        game.step_frames(2);
+
+        assert_eq!(game.state(), AppState::GameOver);
+    }
+
+    #[test]
+    fn player_levels_up_with_score() {
+        let mut game = TestGame::new();
+        game.player_state_mut().score = 1001;
+
+        game.step();
+
+        assert_eq!(game.player_state().level, 2);
+        assert_eq!(game.game().level, 2);
+        let events = game.app.world.resource::<Events<LevelUpEvent>>();
+        assert_eq!(events.get_reader().iter(events).count(), 1);
+    }
+
+    #[test]
+    fn player_level_is_capped() {
+        let mut game = TestGame::new();
+        game.player_state_mut().score = 7999;
+
+        game.step_frames(10);
+
+        assert_eq!(game.player_state().level, 5);
+        assert_eq!(game.state(), AppState::InGame);
+    }
+
+    #[test]
+    fn restart_after_game_over_starts_a_fresh_game() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("trespasser", Vec2::new(0.0, 200.0));
+        game.player_state_mut().score = 2500;
+        game.step_frames(2);
+        let player = game.player();
+        game.app
+            .world
+            .get_mut::<Player>(player)
+            .unwrap()
+            .current_weapon_index = 2;
+        game.player_state_mut().lives = 1;
+        game.player_state_mut().health = 0;
+        game.step_frames(2);
+        assert_eq!(game.state(), AppState::GameOver);
+
+        game.tap(KeyCode::R);
+        game.step();
+
+        assert_eq!(game.state(), AppState::InGame);
+        assert!(!game.exists(enemy));
+        assert_eq!(game.player_state().health, PLAYER_HEALTH);
+        assert_eq!(game.player_state().lives, PLAYER_LIVES);
+        assert_eq!(game.player_state().score, 0);
+        assert_eq!(game.player_state().level, 1);
+        assert_eq!(game.game().level, 1);
+        let player = game.app.world.get::<Player>(player).unwrap();
+        assert_eq!(player.current_weapon_index, 0);
+    }
+
+    #[test]
+    fn escape_after_game_over_returns_to_menu() {
+        let mut game = TestGame::new();
+        game.app
+            .init_resource::<HighScores>()
+            .add_plugin(menu::MenuPlugin);
+        game.game_mut().earth_health = 0;
+        game.step_frames(2);
+
+        game.tap(KeyCode::Escape);
+        game.step();
+
+        assert_eq!(game.state(), AppState::MainMenu);
+        assert_eq!(game.game().earth_health, EARTH_HEALTH);
+
+        game.tap(KeyCode::Return);
+        game.step();
+        assert_eq!(game.state(), AppState::InGame);
     }
 }
@@ -0,0 +1,480 @@
+//! Main menu shown before every game, on top of the scrolling backdrop.
+
+use bevy::prelude::*;
+
+use crate::{
+    controls::{key_name, Action, ActionState, KeyBindings},
+    coop::GameMode,
+    high_scores::HighScores,
+    settings::Settings,
+    AppState, Player,
+};
+
+const BUTTON_COLOR: Color = Color::NONE;
+const SELECTED_BUTTON_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+const SELECTED_TEXT_COLOR: Color = Color::rgb(1.0, 0.8, 0.3);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(enter_menu.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_systems(
+                (
+                    menu_actions,
+                    menu_mouse,
+                    rebind_key.after(menu_actions).after(menu_mouse),
+                    show_menu.after(rebind_key),
+                )
+                    .in_set(OnUpdate(AppState::MainMenu)),
+            )
+            .add_system(exit_menu.in_schedule(OnExit(AppState::MainMenu)));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum MenuScreen {
+    #[default]
+    Main,
+    Settings,
+    HighScores,
+    Controls,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MenuAction {
+    Start(GameMode),
+    Open(MenuScreen),
+    Back,
+    Rebind(Action),
+    ResetBindings,
+    ToggleHoldToFire,
+    TogglePointerMovement,
+}
+
+impl MenuScreen {
+    fn title(&self) -> &'static str {
+        match self {
+            MenuScreen::Main => "THE SPACESHIP PROJECT",
+            MenuScreen::Settings => "SETTINGS",
+            MenuScreen::HighScores => "HIGH SCORES",
+            MenuScreen::Controls => "CONTROLS",
+        }
+    }
+
+    fn lines(&self, high_scores: &HighScores, bindings: &KeyBindings) -> Vec<String> {
+        let keys = |actions: &[Action]| {
+            actions
+                .iter()
+                .flat_map(|action| bindings.keys(*action))
+                .map(|key| key_name(*key))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        match self {
+            MenuScreen::Main | MenuScreen::Settings => vec![],
+            MenuScreen::HighScores if high_scores.entries.is_empty() => {
+                vec!["No high scores yet.".into()]
+            }
+            MenuScreen::HighScores => high_scores
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    format!(
+                        "{}. {} - {} - Level {} - {} - Seed {}",
+                        i + 1,
+                        entry.name,
+                        entry.score,
+                        entry.level,
+                        entry.date,
+                        entry.seed
+                    )
+                })
+                .collect(),
+            MenuScreen::Controls => vec![
+                format!(
+                    "{} / Left stick - Move your spaceship",
+                    keys(&[
+                        Action::MoveUp,
+                        Action::MoveLeft,
+                        Action::MoveDown,
+                        Action::MoveRight
+                    ])
+                ),
+                format!("{} / A - Fire", keys(&[Action::Fire])),
+                format!(
+                    "{}, {} / Shoulder buttons - Switch weapons",
+                    keys(&Action::WEAPONS),
+                    keys(&[Action::PreviousWeapon, Action::NextWeapon])
+                ),
+                "Touch or mouse - Steer your spaceship and fire".into(),
+                "Player 2 in co-op: Arrows / Second gamepad - Move, RCONTROL NUMPAD0 - Fire, \
+                 NUMPAD1-5, DELETE PAGEDOWN - Switch weapons"
+                    .into(),
+                format!("{} / Start - Pause", keys(&[Action::Pause])),
+                "R / Start - Restart once the game is over".into(),
+                "Keys can be changed in the settings".into(),
+                "".into(),
+                "Created by Joel Ambass - Based on the original ssp project (ssp-js.joel.am)"
+                    .into(),
+            ],
+        }
+    }
+
+    fn buttons(&self) -> Vec<MenuAction> {
+        match self {
+            MenuScreen::Main => vec![
+                MenuAction::Start(GameMode::Solo),
+                MenuAction::Start(GameMode::Coop),
+                MenuAction::Open(MenuScreen::Settings),
+                MenuAction::Open(MenuScreen::HighScores),
+                MenuAction::Open(MenuScreen::Controls),
+            ],
+            MenuScreen::Settings => Action::REBINDABLE
+                .into_iter()
+                .map(MenuAction::Rebind)
+                .chain([
+                    MenuAction::ToggleHoldToFire,
+                    MenuAction::TogglePointerMovement,
+                    MenuAction::ResetBindings,
+                    MenuAction::Back,
+                ])
+                .collect(),
+            _ => vec![MenuAction::Back],
+        }
+    }
+}
+
+impl MenuAction {
+    fn label(&self, bindings: &KeyBindings, settings: &Settings, rebinding: bool) -> String {
+        match self {
+            MenuAction::Start(GameMode::Solo) => "START".into(),
+            MenuAction::Start(GameMode::Coop) => "START CO-OP".into(),
+            MenuAction::Open(screen) => screen.title().into(),
+            MenuAction::Back => "BACK".into(),
+            MenuAction::Rebind(action) if rebinding => {
+                format!("{}: PRESS A KEY (ESC TO CANCEL)", action.name())
+            }
+            MenuAction::Rebind(action) => {
+                let keys: Vec<_> = bindings
+                    .keys(*action)
+                    .iter()
+                    .map(|key| key_name(*key))
+                    .collect();
+                if keys.is_empty() {
+                    format!("{}: -", action.name())
+                } else {
+                    format!("{}: {}", action.name(), keys.join(" "))
+                }
+            }
+            MenuAction::ResetBindings => "RESET KEYS TO DEFAULTS".into(),
+            MenuAction::ToggleHoldToFire if settings.hold_to_fire => "HOLD TO FIRE: ON".into(),
+            MenuAction::ToggleHoldToFire => "HOLD TO FIRE: OFF".into(),
+            MenuAction::TogglePointerMovement if settings.pointer_movement => {
+                "FOLLOW THE MOUSE: ON".into()
+            }
+            MenuAction::TogglePointerMovement => "FOLLOW THE MOUSE: OFF".into(),
+        }
+    }
+}
+
+/// Which screen of the menu is shown and which of its buttons is selected.
+#[derive(Resource, Default)]
+struct Menu {
+    screen: MenuScreen,
+    selected: usize,
+    /// Waiting for the next key press to bind it to this action.
+    rebinding: Option<Action>,
+}
+
+impl Menu {
+    fn activate(
+        &mut self,
+        action: MenuAction,
+        next_state: &mut NextState<AppState>,
+        mode: &mut GameMode,
+        bindings: &mut KeyBindings,
+        settings: &mut Settings,
+    ) {
+        match action {
+            MenuAction::Start(start_mode) => {
+                *mode = start_mode;
+                next_state.set(AppState::InGame);
+            }
+            MenuAction::Open(screen) => {
+                *self = Menu {
+                    screen,
+                    ..default()
+                }
+            }
+            MenuAction::Back => {
+                // Select the button that led to the screen we are coming from.
+                let opened_by = MenuAction::Open(self.screen);
+                *self = Menu {
+                    screen: MenuScreen::Main,
+                    selected: MenuScreen::Main
+                        .buttons()
+                        .iter()
+                        .position(|action| *action == opened_by)
+                        .unwrap_or(0),
+                    rebinding: None,
+                }
+            }
+            MenuAction::Rebind(action) => self.rebinding = Some(action),
+            MenuAction::ResetBindings => {
+                *bindings = KeyBindings::default();
+                bindings.save();
+            }
+            MenuAction::ToggleHoldToFire => {
+                settings.hold_to_fire = !settings.hold_to_fire;
+                settings.save();
+            }
+            MenuAction::TogglePointerMovement => {
+                settings.pointer_movement = !settings.pointer_movement;
+                settings.save();
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct MenuButton(usize);
+
+fn enter_menu(mut commands: Commands, mut player_query: Query<&mut Visibility, With<Player>>) {
+    commands.insert_resource(Menu::default());
+
+    for mut visibility in &mut player_query {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn exit_menu(
+    mut commands: Commands,
+    root_query: Query<Entity, With<MenuRoot>>,
+    mut player_query: Query<&mut Visibility, With<Player>>,
+) {
+    for root in &root_query {
+        commands.entity(root).despawn_recursive();
+    }
+    commands.remove_resource::<Menu>();
+
+    for mut visibility in &mut player_query {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn menu_actions(
+    actions: Res<ActionState>,
+    mut menu: ResMut<Menu>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut mode: ResMut<GameMode>,
+    mut bindings: ResMut<KeyBindings>,
+    mut settings: ResMut<Settings>,
+) {
+    if menu.rebinding.is_some() {
+        return;
+    }
+
+    let buttons = menu.screen.buttons();
+
+    // Gameplay keys work as well, unless they were bound to something else.
+    if actions.any_just_pressed([Action::Up, Action::MoveUp]) {
+        menu.selected = (menu.selected + buttons.len() - 1) % buttons.len();
+    }
+    if actions.any_just_pressed([Action::Down, Action::MoveDown]) {
+        menu.selected = (menu.selected + 1) % buttons.len();
+    }
+    if actions.any_just_pressed([Action::Confirm, Action::Fire]) {
+        let action = buttons[menu.selected];
+        menu.activate(
+            action,
+            &mut next_state,
+            &mut mode,
+            &mut bindings,
+            &mut settings,
+        );
+    } else if actions.just_pressed(Action::Back) && menu.screen != MenuScreen::Main {
+        menu.activate(
+            MenuAction::Back,
+            &mut next_state,
+            &mut mode,
+            &mut bindings,
+            &mut settings,
+        );
+    }
+}
+
+fn menu_mouse(
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut menu: ResMut<Menu>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut mode: ResMut<GameMode>,
+    mut bindings: ResMut<KeyBindings>,
+    mut settings: ResMut<Settings>,
+) {
+    if menu.rebinding.is_some() {
+        return;
+    }
+
+    for (interaction, button) in &interaction_query {
+        match interaction {
+            Interaction::Clicked => {
+                let action = menu.screen.buttons()[button.0];
+                menu.activate(
+                    action,
+                    &mut next_state,
+                    &mut mode,
+                    &mut bindings,
+                    &mut settings,
+                );
+                return;
+            }
+            Interaction::Hovered => {
+                // Only touch the menu if needed, every change rebuilds it.
+                if menu.selected != button.0 {
+                    menu.selected = button.0;
+                }
+            }
+            Interaction::None => {}
+        }
+    }
+}
+
+fn rebind_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut menu: ResMut<Menu>,
+    mut bindings: ResMut<KeyBindings>,
+    mut listening: Local<bool>,
+) {
+    let Some(action) = menu.rebinding else {
+        *listening = false;
+        return;
+    };
+
+    // The key that started the rebinding is still pressed in that frame, don't bind it.
+    if !*listening {
+        *listening = true;
+        return;
+    }
+
+    if let Some(key) = keyboard_input.get_just_pressed().next() {
+        if *key != KeyCode::Escape {
+            if bindings.bind(action, *key) {
+                bindings.save();
+            } else {
+                warn!(
+                    "{} is kept for the menu or the second player",
+                    key_name(*key)
+                );
+            }
+        }
+        menu.rebinding = None;
+        *listening = false;
+    }
+}
+
+/// Rebuilds the whole menu whenever it changes, it's only a handful of nodes.
+fn show_menu(
+    mut commands: Commands,
+    menu: Res<Menu>,
+    root_query: Query<Entity, With<MenuRoot>>,
+    high_scores: Res<HighScores>,
+    bindings: Res<KeyBindings>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+) {
+    if !menu.is_changed() && !bindings.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for root in &root_query {
+        commands.entity(root).despawn_recursive();
+    }
+
+    let font = asset_server.load("fonts/impact.ttf");
+    let text_style = |font_size, color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(menu.screen.title(), text_style(60.0, Color::WHITE))
+                    .with_style(Style {
+                        margin: UiRect::bottom(Val::Px(24.0)),
+                        ..default()
+                    }),
+            );
+
+            for line in menu.screen.lines(&high_scores, &bindings) {
+                parent.spawn(
+                    TextBundle::from_section(line, text_style(26.0, Color::WHITE))
+                        .with_text_alignment(TextAlignment::Center),
+                );
+            }
+
+            // The settings list every action, so their buttons have to be smaller.
+            let (button_height, font_size) = match menu.screen {
+                MenuScreen::Settings => (24.0, 22.0),
+                _ => (60.0, 42.0),
+            };
+
+            for (i, action) in menu.screen.buttons().iter().enumerate() {
+                let selected = i == menu.selected;
+                let label =
+                    action.label(&bindings, &settings, selected && menu.rebinding.is_some());
+
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(480.0), Val::Px(button_height)),
+                                margin: UiRect::top(Val::Px(button_height / 5.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: if selected {
+                                SELECTED_BUTTON_COLOR
+                            } else {
+                                BUTTON_COLOR
+                            }
+                            .into(),
+                            ..default()
+                        },
+                        MenuButton(i),
+                    ))
+                    .with_children(|button| {
+                        let color = if selected {
+                            SELECTED_TEXT_COLOR
+                        } else {
+                            Color::WHITE
+                        };
+                        button.spawn(TextBundle::from_section(
+                            label,
+                            text_style(font_size, color),
+                        ));
+                    });
+            }
+        });
+}
@@ -0,0 +1,355 @@
+//! How enemy ships fly across the playfield. Each ship follows the pattern of its archetype,
+//! unless the wave entry spawning it picks another one.
+
+use std::f32::consts::{FRAC_2_PI, TAU};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    collision::{Collider, CollisionGridSystem},
+    lives::Respawning,
+    playfield::PLAYFIELD,
+    simulation::TickSet,
+    Enemy, Player,
+};
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            enemy_movement
+                .before(CollisionGridSystem)
+                .in_set(TickSet::Gameplay)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Durations are in seconds, distances in pixels. Ships fly down at their own speed
+/// whenever the pattern doesn't say otherwise.
+#[derive(Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum MovementPattern {
+    #[default]
+    Straight,
+    /// Weaves from side to side around the line it appeared on.
+    Weave { amplitude: f32, period: f32 },
+    /// Like `Weave`, but in straight lines with sharp turns.
+    ZigZag { amplitude: f32, period: f32 },
+    /// Flies down for `delay`, then dives at the nearest player `boost` times as fast.
+    Dive { delay: f32, boost: f32 },
+    /// Crosses the playfield sideways at `speed` pixels per tick, turning at the edges.
+    Strafe { speed: f32 },
+    /// Stops `height` pixels above the center of the playfield for `duration`, then flies on.
+    Hover { height: f32, duration: f32 },
+    /// Follows a Bezier curve for `duration`, then flies straight down. The curve starts where
+    /// the ship appeared, the points are offsets from there and the last one is the end.
+    Bezier {
+        points: Vec<(f32, f32)>,
+        duration: f32,
+    },
+}
+
+impl MovementPattern {
+    /// How far the pattern swings to the sides of the line the ship appeared on, it has to
+    /// appear far enough from the edges to stay on the playfield.
+    pub fn sway_width(&self) -> f32 {
+        match *self {
+            Self::Weave { amplitude, .. } | Self::ZigZag { amplitude, .. } => amplitude.abs(),
+            _ => 0.0,
+        }
+    }
+
+    /// Sideways offset from the line the ship appeared on after `time`.
+    fn sway(&self, time: f32) -> f32 {
+        match *self {
+            Self::Weave { amplitude, period } => amplitude * (TAU * time / period).sin(),
+            Self::ZigZag { amplitude, period } => {
+                amplitude * FRAC_2_PI * (TAU * time / period).sin().asin()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Patterns only ever move the ship by a step, so pushback from hits still sticks.
+#[derive(Component)]
+pub struct Movement {
+    pub pattern: MovementPattern,
+    /// Time since the ship appeared.
+    elapsed: f32,
+    /// Direction of a dive or strafe once it started.
+    heading: Option<Vec2>,
+    /// Time spent hovering so far.
+    hovered: f32,
+}
+
+impl Movement {
+    pub fn new(pattern: MovementPattern) -> Self {
+        Self {
+            pattern,
+            elapsed: 0.0,
+            heading: None,
+            hovered: 0.0,
+        }
+    }
+
+    /// How far to move in a tick of `delta` seconds, for ships at `position` flying down at `speed`
+    /// that have to stay within `max_x` of the center. Dives aim at the nearest of the `players`.
+    pub fn step(
+        &mut self,
+        position: Vec2,
+        speed: f32,
+        max_x: f32,
+        delta: f32,
+        players: impl Iterator<Item = Vec2>,
+    ) -> Vec2 {
+        let time = self.elapsed;
+        self.elapsed += delta;
+        let down = Vec2::new(0.0, -speed);
+
+        match self.pattern {
+            MovementPattern::Straight => down,
+            MovementPattern::Weave { .. } | MovementPattern::ZigZag { .. } => {
+                let sway = self.pattern.sway(time + delta) - self.pattern.sway(time);
+                down + Vec2::new(sway, 0.0)
+            }
+            MovementPattern::Dive { delay, boost } => {
+                if time < delay {
+                    down
+                } else {
+                    // Ships below every player just keep flying down.
+                    let heading = *self.heading.get_or_insert_with(|| {
+                        players
+                            .map(|player| player - position)
+                            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+                            .map(|offset| offset.normalize_or_zero())
+                            .filter(|heading| heading.y < 0.0)
+                            .unwrap_or(Vec2::NEG_Y)
+                    });
+                    heading * speed * boost
+                }
+            }
+            MovementPattern::Strafe {
+                speed: strafe_speed,
+            } => {
+                let x = position.x;
+                let heading =
+                    self.heading
+                        .get_or_insert(if x < 0.0 { Vec2::X } else { Vec2::NEG_X });
+                if (x <= -max_x && heading.x < 0.0) || (x >= max_x && heading.x > 0.0) {
+                    heading.x = -heading.x;
+                }
+                down + *heading * strafe_speed
+            }
+            MovementPattern::Hover { height, duration } => {
+                if self.hovered >= duration {
+                    down
+                } else if position.y > height {
+                    // Stops right at the height instead of overshooting it.
+                    Vec2::new(0.0, (height - position.y).max(-speed))
+                } else {
+                    self.hovered += delta;
+                    Vec2::ZERO
+                }
+            }
+            MovementPattern::Bezier {
+                ref points,
+                duration,
+            } => {
+                if time < duration {
+                    let progress = |time: f32| (time / duration).min(1.0);
+                    bezier(points, progress(time + delta)) - bezier(points, progress(time))
+                } else {
+                    down
+                }
+            }
+        }
+    }
+}
+
+/// Point at `t` from 0.0 to 1.0 along the curve starting at the origin, by de Casteljau's algorithm.
+fn bezier(points: &[(f32, f32)], t: f32) -> Vec2 {
+    let mut points: Vec<Vec2> = std::iter::once(Vec2::ZERO)
+        .chain(points.iter().map(|&(x, y)| Vec2::new(x, y)))
+        .collect();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| pair[0].lerp(pair[1], t))
+            .collect();
+    }
+    points[0]
+}
+
+pub(crate) fn enemy_movement(
+    fixed_time: Res<FixedTime>,
+    mut enemy_query: Query<(&Enemy, &mut Movement, &mut Transform, &Collider), Without<Player>>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (enemy, mut movement, mut transform, collider) in &mut enemy_query {
+        let step = movement.step(
+            transform.translation.truncate(),
+            enemy.speed,
+            PLAYFIELD.x / 2.0 - collider.size.x / 2.0,
+            delta,
+            player_query
+                .iter()
+                .map(|player| player.translation.truncate()),
+        );
+        transform.translation += step.extend(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{Movement, MovementPattern};
+    use crate::{collision::Collider, playfield::PLAYFIELD, test_harness::TestGame, Enemy};
+
+    fn spawn(game: &mut TestGame, pattern: MovementPattern, position: Vec2) -> Entity {
+        let enemy = game.spawn_enemy("trespasser", position);
+        game.app
+            .world
+            .entity_mut(enemy)
+            .insert(Movement::new(pattern));
+        enemy
+    }
+
+    #[test]
+    fn weave_swings_back_to_its_line() {
+        let mut game = TestGame::new();
+        let pattern = MovementPattern::Weave {
+            amplitude: 40.0,
+            period: 1.0,
+        };
+        let enemy = spawn(&mut game, pattern, Vec2::new(0.0, 250.0));
+
+        game.step_frames(10);
+        assert!((game.position(enemy).x - 40.0).abs() < 0.1);
+
+        game.step_frames(30);
+        let position = game.position(enemy);
+        assert!(position.x.abs() < 0.1);
+        assert!(position.y < 250.0);
+    }
+
+    #[test]
+    fn swaying_ships_stay_on_the_playfield() {
+        let waves = ron::from_str(
+            r#"#![enable(implicit_some)]
+            (
+                levels: [
+                    (waves: [(entries: [(archetype: "big_ship", count: 2, interval: 60.0, position: At(0.0))])]),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let mut game = TestGame::with_waves(waves);
+        game.step();
+        let ship = game
+            .app
+            .world
+            .query_filtered::<Entity, With<Enemy>>()
+            .single(&game.app.world);
+        let half_width = game.app.world.get::<Collider>(ship).unwrap().size.x / 2.0;
+
+        for _ in 0..160 {
+            game.step();
+            assert!(game.position(ship).x - half_width >= -PLAYFIELD.x / 2.0);
+        }
+    }
+
+    #[test]
+    fn wave_entries_replace_the_pattern_of_the_archetype() {
+        let waves = ron::from_str(
+            r#"#![enable(implicit_some)]
+            (
+                levels: [
+                    (waves: [(entries: [
+                        (archetype: "trespasser", count: 2, interval: 60.0),
+                        (archetype: "trespasser", count: 2, interval: 60.0, movement: Strafe(speed: 2.0)),
+                    ])]),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let mut game = TestGame::with_waves(waves);
+        game.step();
+
+        let mut patterns: Vec<MovementPattern> = game
+            .app
+            .world
+            .query::<&Movement>()
+            .iter(&game.app.world)
+            .map(|movement| movement.pattern.clone())
+            .collect();
+        patterns.sort_by_key(|pattern| matches!(pattern, MovementPattern::Strafe { .. }));
+        assert_eq!(
+            patterns,
+            [
+                MovementPattern::Weave {
+                    amplitude: 40.0,
+                    period: 2.0
+                },
+                MovementPattern::Strafe { speed: 2.0 }
+            ]
+        );
+    }
+
+    #[test]
+    fn hover_stops_for_a_while() {
+        let mut game = TestGame::new();
+        let pattern = MovementPattern::Hover {
+            height: 100.0,
+            duration: 1.0,
+        };
+        let enemy = spawn(&mut game, pattern, Vec2::new(300.0, 150.0));
+
+        game.step_frames(40);
+        assert_eq!(game.position(enemy).y, 100.0);
+
+        game.step_frames(30);
+        assert_eq!(game.position(enemy).y, 100.0);
+
+        game.step_frames(20);
+        assert!(game.position(enemy).y < 100.0);
+    }
+
+    #[test]
+    fn dive_heads_for_the_player() {
+        let mut game = TestGame::new();
+        let player = game.player();
+        let pattern = MovementPattern::Dive {
+            delay: 0.0,
+            boost: 2.0,
+        };
+        let enemy = spawn(&mut game, pattern, Vec2::new(300.0, 200.0));
+        let start = game.position(enemy).truncate();
+        let towards_player = (game.position(player).truncate() - start).normalize();
+
+        game.step_frames(10);
+
+        let heading = (game.position(enemy).truncate() - start).normalize();
+        assert!(heading.dot(towards_player) > 0.999);
+    }
+
+    #[test]
+    fn bezier_ends_at_its_last_point() {
+        let mut game = TestGame::new();
+        let pattern = MovementPattern::Bezier {
+            points: vec![(200.0, 0.0), (100.0, -100.0)],
+            duration: 1.0,
+        };
+        let enemy = spawn(&mut game, pattern, Vec2::new(0.0, 200.0));
+
+        game.step_frames(40);
+
+        let position = game.position(enemy).truncate();
+        assert!(position.distance(Vec2::new(100.0, 100.0)) < 0.1);
+    }
+}
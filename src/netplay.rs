@@ -0,0 +1,751 @@
+//! Co-op over the network (`--host <port>` and `--join <address:port>`, native builds only).
+//! Both games simulate the same ticks in lockstep: every tick each one sends its input to the other
+//! and a tick only runs once the inputs of both players are there. Inputs are delayed by a few ticks,
+//! so usually they arrive before they are needed and nobody has to wait.
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::Instant};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    controls::{Action, ActionState, ActionSystem},
+    coop::GameMode,
+    high_scores::NameEntry,
+    launch_options::launch_option,
+    replay::ReplayActions,
+    rng::GameRng,
+    settings::Settings,
+    simulation::{TickInput, TickSet},
+    AppState,
+};
+
+const MAGIC: &[u8; 4] = b"SSPN";
+const VERSION: u8 = 1;
+/// Ticks between pressing a key and the ship reacting, enough for the round trip on a LAN.
+const INPUT_DELAY: usize = 3;
+/// Inputs the other player doesn't have yet are sent again with every packet, up to this many.
+const MAX_INPUTS_PER_PACKET: usize = 64;
+const HELLO_INTERVAL: Duration = Duration::from_millis(500);
+/// Without a packet from the other player for this long, the connection is lost.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub enum NetplayPlugin {
+    Off,
+    /// Waits on the port for the other player, who gets the second ship.
+    Host(u16),
+    Join(String),
+}
+
+impl NetplayPlugin {
+    pub fn from_launch_options() -> Self {
+        if let Some(port) = launch_option("host") {
+            let port = port
+                .parse()
+                .unwrap_or_else(|_| panic!("Not a port to host on: `{port}`"));
+            Self::Host(port)
+        } else if let Some(address) = launch_option("join") {
+            Self::Join(address)
+        } else {
+            Self::Off
+        }
+    }
+}
+
+impl Plugin for NetplayPlugin {
+    fn build(&self, app: &mut App) {
+        let session = match self {
+            NetplayPlugin::Off => return,
+            NetplayPlugin::Host(port) => NetSession::host(*port),
+            NetplayPlugin::Join(address) => NetSession::join(address),
+        };
+        let session =
+            session.unwrap_or_else(|err| panic!("Could not open the netplay connection: {err}"));
+
+        app.insert_resource(session)
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule
+                    .configure_set(TickSet::Gameplay.run_if(inputs_ready))
+                    .configure_set(TickSet::Interpolation.run_if(inputs_ready));
+            })
+            .add_system(connect.in_set(OnUpdate(AppState::MainMenu)))
+            .add_system(
+                collect_local_input
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(ActionSystem),
+            )
+            .add_system(
+                exchange_inputs
+                    .before(TickSet::Gameplay)
+                    .run_if(in_state(AppState::InGame))
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    keep_alive,
+                    restart_together.run_if(not(resource_exists::<NameEntry>())),
+                )
+                    .chain()
+                    .distributive_run_if(online)
+                    .in_set(OnUpdate(AppState::GameOver)),
+            )
+            .add_system(
+                end_session
+                    .run_if(resource_exists::<NetSession>())
+                    .run_if(not(online))
+                    .run_if(not(resource_exists::<NameEntry>()))
+                    .in_set(OnUpdate(AppState::GameOver)),
+            )
+            .add_systems(
+                (
+                    keep_alive,
+                    restart_together.run_if(not(resource_exists::<NameEntry>())),
+                )
+                    .chain()
+                    .distributive_run_if(online)
+                    .in_set(OnUpdate(AppState::GameWon)),
+            )
+            .add_system(
+                end_session
+                    .run_if(resource_exists::<NetSession>())
+                    .run_if(not(online))
+                    .run_if(not(resource_exists::<NameEntry>()))
+                    .in_set(OnUpdate(AppState::GameWon)),
+            );
+    }
+}
+
+/// Whether a game is played with someone over the network. Pausing and restarting alone
+/// are off then, the other game would have to wait.
+pub fn online(session: Option<Res<NetSession>>) -> bool {
+    session.is_some_and(|session| session.connected)
+}
+
+/// Index of the player steered on this machine, the second one for whoever joined.
+pub fn local_player(session: Option<&NetSession>) -> usize {
+    session.map_or(0, |session| session.local_index)
+}
+
+/// Ticks without the inputs of both players neither simulate nor clear the inputs.
+fn inputs_ready(session: Option<Res<NetSession>>, state: Res<State<AppState>>) -> bool {
+    state.0 != AppState::InGame || session.is_none_or(|session| session.ready)
+}
+
+enum Packet {
+    /// Sent by the one joining until the host answers.
+    Hello,
+    Welcome {
+        seed: u64,
+        hold_to_fire: bool,
+    },
+    Inputs {
+        /// Counts the restarts, inputs of another game are ignored.
+        round: u8,
+        /// Inputs received from the other player so far, they don't need to be sent again.
+        received: u32,
+        first_tick: u32,
+        inputs: Vec<ReplayActions>,
+    },
+    /// The other player went back to the menu.
+    Leave,
+}
+
+impl Packet {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        match self {
+            Packet::Hello => bytes.push(0),
+            Packet::Welcome { seed, hold_to_fire } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+                bytes.push(*hold_to_fire as u8);
+            }
+            Packet::Inputs {
+                round,
+                received,
+                first_tick,
+                inputs,
+            } => {
+                bytes.push(2);
+                bytes.push(*round);
+                bytes.extend_from_slice(&received.to_le_bytes());
+                bytes.extend_from_slice(&first_tick.to_le_bytes());
+                for actions in inputs {
+                    actions.write(&mut bytes);
+                }
+            }
+            Packet::Leave => bytes.push(3),
+        }
+        bytes
+    }
+
+    /// Anything that isn't a packet of this version is ignored.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return None;
+        }
+
+        let body = &bytes[6..];
+        match bytes[5] {
+            0 => Some(Packet::Hello),
+            1 if body.len() == 9 => Some(Packet::Welcome {
+                seed: u64::from_le_bytes(body[0..8].try_into().unwrap()),
+                hold_to_fire: body[8] != 0,
+            }),
+            2 if body.len() >= 9 && (body.len() - 9).is_multiple_of(ReplayActions::LEN) => {
+                Some(Packet::Inputs {
+                    round: body[0],
+                    received: u32::from_le_bytes(body[1..5].try_into().unwrap()),
+                    first_tick: u32::from_le_bytes(body[5..9].try_into().unwrap()),
+                    inputs: body[9..]
+                        .chunks_exact(ReplayActions::LEN)
+                        .map(ReplayActions::read)
+                        .collect(),
+                })
+            }
+            3 => Some(Packet::Leave),
+            _ => None,
+        }
+    }
+}
+
+/// The connection to the other player and the inputs of both.
+#[derive(Resource)]
+pub struct NetSession {
+    socket: UdpSocket,
+    /// Only known to the host once the other player said hello.
+    peer: Option<SocketAddr>,
+    /// The host flies the first ship, the one joining the second.
+    local_index: usize,
+    connected: bool,
+    /// For the host to answer a repeated hello, in case the welcome got lost.
+    welcome: Option<Packet>,
+    last_hello: Option<Instant>,
+    last_received: Instant,
+    /// Seeds of the restarted games, the same in both games.
+    seeds: ChaCha8Rng,
+    round: u8,
+    peer_restarted: bool,
+    /// Local actions since the last tick, see `TickInput`.
+    pending: ActionState,
+    /// Next tick to simulate.
+    tick: usize,
+    /// Inputs by tick, the local ones reach `INPUT_DELAY` ticks ahead.
+    local_inputs: Vec<ReplayActions>,
+    remote_inputs: Vec<ReplayActions>,
+    /// Local inputs the other player has received.
+    acknowledged: usize,
+    /// The current tick has the inputs of both players.
+    ready: bool,
+}
+
+impl NetSession {
+    fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        info!(
+            "Waiting for the other player on port {}",
+            socket.local_addr()?.port()
+        );
+        Self::new(socket, None, 0)
+    }
+
+    fn join(address: &str) -> io::Result<Self> {
+        let peer = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "address without a host"))?;
+        Self::new(UdpSocket::bind(("0.0.0.0", 0))?, Some(peer), 1)
+    }
+
+    fn new(socket: UdpSocket, peer: Option<SocketAddr>, local_index: usize) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            peer,
+            local_index,
+            connected: false,
+            welcome: None,
+            last_hello: None,
+            last_received: Instant::now(),
+            seeds: ChaCha8Rng::seed_from_u64(0),
+            round: 0,
+            peer_restarted: false,
+            pending: ActionState::default(),
+            tick: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            acknowledged: 0,
+            ready: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    fn send(&self, packet: &Packet) {
+        let Some(peer) = self.peer else {
+            return;
+        };
+        // Lost packets are fine, everything is sent again until it arrives.
+        if let Err(err) = self.socket.send_to(&packet.to_bytes(), peer) {
+            debug!("Could not send to the other player: {err}");
+        }
+    }
+
+    fn send_inputs(&self) {
+        let first_tick = self.acknowledged.min(self.local_inputs.len());
+        let last_tick = self
+            .local_inputs
+            .len()
+            .min(first_tick + MAX_INPUTS_PER_PACKET);
+
+        self.send(&Packet::Inputs {
+            round: self.round,
+            received: self.remote_inputs.len() as u32,
+            first_tick: first_tick as u32,
+            inputs: self.local_inputs[first_tick..last_tick].to_vec(),
+        });
+    }
+
+    /// Takes in everything the other player sent since the last call.
+    fn receive(&mut self) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        let mut buffer = [0; 2048];
+
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    debug!("Could not receive from the other player: {err}");
+                    break;
+                }
+            };
+            if self.peer.is_some_and(|peer| peer != from) {
+                continue;
+            }
+            let Some(packet) = Packet::from_bytes(&buffer[..len]) else {
+                continue;
+            };
+
+            self.last_received = Instant::now();
+            match packet {
+                Packet::Hello if self.peer.is_none() => {
+                    self.peer = Some(from);
+                    packets.push(packet);
+                }
+                Packet::Hello => {
+                    if let Some(welcome) = &self.welcome {
+                        self.send(welcome);
+                    }
+                }
+                Packet::Inputs {
+                    round,
+                    received,
+                    first_tick,
+                    inputs,
+                } => self.receive_inputs(round, received as usize, first_tick as usize, inputs),
+                Packet::Welcome { .. } => packets.push(packet),
+                Packet::Leave => {
+                    if self.connected {
+                        info!("The other player left");
+                    }
+                    self.connected = false;
+                }
+            }
+        }
+        packets
+    }
+
+    fn receive_inputs(
+        &mut self,
+        round: u8,
+        received: usize,
+        first_tick: usize,
+        inputs: Vec<ReplayActions>,
+    ) {
+        if round != self.round {
+            self.peer_restarted |= round == self.round.wrapping_add(1);
+            return;
+        }
+
+        self.acknowledged = self.acknowledged.max(received);
+        let known = self.remote_inputs.len();
+        if first_tick <= known {
+            self.remote_inputs
+                .extend(inputs.into_iter().skip(known - first_tick));
+        }
+    }
+
+    fn start(&mut self, seed: u64) {
+        self.connected = true;
+        self.seeds = ChaCha8Rng::seed_from_u64(seed);
+        info!("Playing together with {}", self.peer.unwrap());
+    }
+
+    /// Starts the next game with a fresh seed, the other one gets the same from its `seeds`.
+    fn restart(&mut self) -> u64 {
+        self.round = self.round.wrapping_add(1);
+        self.peer_restarted = false;
+        self.pending = ActionState::default();
+        self.tick = 0;
+        self.local_inputs.clear();
+        self.remote_inputs.clear();
+        self.acknowledged = 0;
+        self.last_received = Instant::now();
+        self.seeds.gen()
+    }
+}
+
+/// The host waits for a hello and answers with the seed, the one joining says hello until then.
+fn connect(
+    session: Option<ResMut<NetSession>>,
+    mut rng: ResMut<GameRng>,
+    mut settings: ResMut<Settings>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.connected {
+        return;
+    }
+
+    let joining = session.local_index > 0;
+    if joining
+        && session
+            .last_hello
+            .is_none_or(|last_hello| last_hello.elapsed() >= HELLO_INTERVAL)
+    {
+        session.send(&Packet::Hello);
+        session.last_hello = Some(Instant::now());
+    }
+
+    for packet in session.receive() {
+        let seed = match packet {
+            Packet::Hello if !joining => {
+                let welcome = Packet::Welcome {
+                    seed: rng.seed,
+                    hold_to_fire: settings.hold_to_fire(),
+                };
+                session.send(&welcome);
+                session.welcome = Some(welcome);
+                rng.seed
+            }
+            Packet::Welcome { seed, hold_to_fire } if joining => {
+                // Changes how firing works, so both have to play with the host's setting.
+                // Only for this session though, the saved one stays the joiner's own.
+                settings.hold_to_fire_override = Some(hold_to_fire);
+                seed
+            }
+            _ => continue,
+        };
+
+        session.start(seed);
+        *rng = GameRng::new(seed);
+        *mode = GameMode::Coop;
+        next_state.set(AppState::InGame);
+        return;
+    }
+}
+
+fn collect_local_input(actions: Res<ActionState>, session: Option<ResMut<NetSession>>) {
+    let Some(mut session) = session else {
+        return;
+    };
+    session.pending.movement = actions.movement;
+    session.pending.pressed = actions.pressed;
+    session.pending.just_pressed |= actions.just_pressed;
+}
+
+/// Runs before every tick: sends the local input and fills `TickInput` once both players' are there.
+fn exchange_inputs(
+    session: Option<ResMut<NetSession>>,
+    mut tick_input: ResMut<TickInput>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    session.ready = !session.connected;
+    // Both games end at the same tick, but only leave the game with the next frame.
+    if !session.connected || next_state.0.is_some() {
+        return;
+    }
+
+    if session.local_inputs.is_empty() {
+        session.local_inputs = vec![ReplayActions::default(); INPUT_DELAY];
+    }
+    if session.local_inputs.len() <= session.tick + INPUT_DELAY {
+        let mut actions = ReplayActions::default();
+        actions.capture_actions(&session.pending);
+        session.local_inputs.push(actions);
+        session.pending.just_pressed = 0;
+    }
+
+    session.receive();
+    session.send_inputs();
+
+    if session.last_received.elapsed() > TIMEOUT {
+        error!("Lost the connection to the other player");
+        session.connected = false;
+    }
+    if !session.connected {
+        next_state.set(AppState::GameOver);
+        return;
+    }
+
+    let tick = session.tick;
+    let Some(remote) = session.remote_inputs.get(tick) else {
+        return;
+    };
+    remote.apply_actions(tick_input.player_mut(1 - session.local_index));
+    session.local_inputs[tick].apply_actions(tick_input.player_mut(session.local_index));
+    session.tick += 1;
+    session.ready = true;
+}
+
+/// Between games the other player keeps hearing from this one, even while a high score gets its name.
+fn keep_alive(mut session: ResMut<NetSession>) {
+    session.receive();
+    session.send_inputs();
+}
+
+/// Whoever restarts first takes the other one along, leaving for the menu ends the session.
+fn restart_together(
+    mut commands: Commands,
+    mut session: ResMut<NetSession>,
+    actions: Res<ActionState>,
+    mut rng: ResMut<GameRng>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::Restart) || session.peer_restarted {
+        *rng = GameRng::new(session.restart());
+        next_state.set(AppState::InGame);
+    } else if actions.just_pressed(Action::Back) {
+        session.send(&Packet::Leave);
+        commands.remove_resource::<NetSession>();
+        settings.hold_to_fire_override = None;
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+/// Once the other player left or the connection is lost, the co-op game is over for good.
+fn end_session(
+    mut commands: Commands,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    commands.remove_resource::<NetSession>();
+    settings.hold_to_fire_override = None;
+    next_state.set(AppState::MainMenu);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, utils::Instant};
+
+    use super::{local_player, NetSession, NetplayPlugin, Packet, INPUT_DELAY, TIMEOUT};
+    use crate::{replay::ReplayActions, settings::Settings, test_harness::TestGame, AppState};
+
+    fn connected_games() -> (TestGame, TestGame) {
+        let mut host = TestGame::with_netplay(NetplayPlugin::Host(0));
+        let port = host
+            .app
+            .world
+            .resource::<NetSession>()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut guest = TestGame::with_netplay(NetplayPlugin::Join(format!("127.0.0.1:{port}")));
+
+        for _ in 0..10 {
+            guest.step();
+            host.step();
+        }
+        assert_eq!(host.state(), AppState::InGame);
+        assert_eq!(guest.state(), AppState::InGame);
+        (host, guest)
+    }
+
+    fn game_over(game: &mut TestGame) {
+        game.app
+            .world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::GameOver);
+        game.step();
+    }
+
+    fn tick(game: &TestGame) -> usize {
+        game.app.world.resource::<NetSession>().tick
+    }
+
+    #[test]
+    fn inputs_survive_a_round_trip_through_bytes() {
+        let inputs = vec![
+            ReplayActions {
+                movement: [i16::MAX, -3],
+                pressed: 0b101,
+                just_pressed: 0b1,
+            },
+            ReplayActions::default(),
+        ];
+        let packet = Packet::Inputs {
+            round: 2,
+            received: 17,
+            first_tick: 12,
+            inputs: inputs.clone(),
+        };
+
+        let Some(Packet::Inputs {
+            round,
+            received,
+            first_tick,
+            inputs: loaded,
+        }) = Packet::from_bytes(&packet.to_bytes())
+        else {
+            panic!("Inputs packet did not survive");
+        };
+        assert_eq!((round, received, first_tick), (2, 17, 12));
+        assert_eq!(loaded, inputs);
+        assert!(Packet::from_bytes(b"SSPR hello").is_none());
+    }
+
+    #[test]
+    fn both_games_simulate_the_same_ships() {
+        let (mut host, mut guest) = connected_games();
+        let guest_player = guest.players()[1];
+        let start = guest.position(guest_player);
+
+        guest.press(KeyCode::A);
+        host.press(KeyCode::W);
+        for _ in 0..40 {
+            host.step();
+            guest.step();
+        }
+        // The host may be a tick ahead, let the guest catch up.
+        while tick(&guest) < tick(&host) {
+            guest.step();
+        }
+
+        let (host_players, guest_players) = (host.players(), guest.players());
+        for (host_player, guest_player) in host_players.into_iter().zip(guest_players) {
+            assert_eq!(host.position(host_player), guest.position(guest_player));
+        }
+        let guest_ship = guest.position(guest_player);
+        let host_player = guest.players()[0];
+        let host_ship = guest.position(host_player);
+        assert!(guest_ship.x < start.x);
+        assert_eq!(guest_ship.y, start.y);
+        assert!(host_ship.y > 0.0);
+    }
+
+    #[test]
+    fn games_over_keep_the_connection_alive() {
+        let (mut host, mut guest) = connected_games();
+        game_over(&mut host);
+        game_over(&mut guest);
+        guest.tap(KeyCode::R);
+        guest.step();
+        assert_eq!(guest.state(), AppState::InGame);
+
+        guest.app.world.resource_mut::<NetSession>().last_received = Instant::now() - TIMEOUT;
+        host.step();
+        guest.step();
+
+        let session = guest.app.world.resource::<NetSession>();
+        assert!(session.last_received.elapsed() < TIMEOUT);
+        assert_eq!(guest.state(), AppState::InGame);
+    }
+
+    #[test]
+    fn leaving_takes_the_other_player_back_to_the_menu() {
+        let (mut host, mut guest) = connected_games();
+        game_over(&mut host);
+        game_over(&mut guest);
+
+        host.tap(KeyCode::Escape);
+        host.step();
+        guest.step_frames(2);
+
+        assert_eq!(host.state(), AppState::MainMenu);
+        assert_eq!(guest.state(), AppState::MainMenu);
+        assert!(guest.app.world.get_resource::<NetSession>().is_none());
+    }
+
+    #[test]
+    fn the_one_joining_plays_with_the_setting_of_the_host_without_keeping_it() {
+        let mut host = TestGame::with_netplay(NetplayPlugin::Host(0));
+        let hold_to_fire = !host.app.world.resource::<Settings>().hold_to_fire;
+        host.app.world.resource_mut::<Settings>().hold_to_fire = hold_to_fire;
+        let port = host
+            .app
+            .world
+            .resource::<NetSession>()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut guest = TestGame::with_netplay(NetplayPlugin::Join(format!("127.0.0.1:{port}")));
+        for _ in 0..10 {
+            guest.step();
+            host.step();
+        }
+
+        let settings = guest.app.world.resource::<Settings>();
+        assert_eq!(settings.hold_to_fire(), hold_to_fire);
+        assert_eq!(settings.hold_to_fire, !hold_to_fire);
+
+        game_over(&mut host);
+        game_over(&mut guest);
+        host.tap(KeyCode::Escape);
+        guest.step_frames(3);
+
+        assert_eq!(guest.state(), AppState::MainMenu);
+        assert_eq!(
+            guest.app.world.resource::<Settings>().hold_to_fire(),
+            !hold_to_fire
+        );
+    }
+
+    #[test]
+    fn lost_connection_ends_the_game() {
+        let (mut host, _guest) = connected_games();
+
+        host.app.world.resource_mut::<NetSession>().last_received = Instant::now() - TIMEOUT;
+        host.step_frames(3);
+
+        assert_eq!(host.state(), AppState::MainMenu);
+        assert!(host.app.world.get_resource::<NetSession>().is_none());
+    }
+
+    #[test]
+    fn pointers_steer_the_ship_of_the_one_joining() {
+        let (host, guest) = connected_games();
+
+        assert_eq!(local_player(host.app.world.get_resource()), 0);
+        assert_eq!(local_player(guest.app.world.get_resource()), 1);
+        assert_eq!(local_player(None), 0);
+    }
+
+    #[test]
+    fn game_waits_for_the_other_player() {
+        let (mut host, _guest) = connected_games();
+        let waiting = tick(&host);
+
+        host.step_frames(20);
+
+        assert!(tick(&host) <= waiting + INPUT_DELAY);
+        assert_eq!(host.state(), AppState::InGame);
+    }
+}
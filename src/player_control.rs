@@ -1,98 +1,133 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
 
-use crate::{AppState, Player, ORIGINAL_TARGET_FPS};
+use crate::{
+    collision::Collider,
+    lives::Respawning,
+    playfield::PLAYFIELD,
+    simulation::{TickInput, TickSet},
+    Player,
+};
 
 pub struct PlayerControlPlugin;
 
 impl Plugin for PlayerControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems((player_movement, player_border).in_set(OnUpdate(AppState::InGame)));
+        app.add_systems(
+            (player_movement, player_border)
+                .in_set(TickSet::Gameplay)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
     }
 }
 
 fn player_movement(
-    time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut sprite_position: Query<(&mut Player, &mut Transform, &Handle<Image>)>,
-    assets: Res<Assets<Image>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    tick_input: Res<TickInput>,
+    mut sprite_position: Query<(&mut Player, &mut Transform, &Collider), Without<Respawning>>,
 ) {
-    let window = window_query.single();
+    for (player, mut transform, collider) in &mut sprite_position {
+        let player_size = collider.size;
 
-    for (player, mut transform, img_handle) in &mut sprite_position {
-        let player_size = assets.get(img_handle).unwrap().size();
+        let movement = tick_input.player(player.index).movement;
 
-        if keyboard_input.pressed(KeyCode::W) {
-            let new_y =
-                transform.translation.y + player.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
-            if valid_move(transform.translation.x, new_y, window, player_size) {
+        if movement.y != 0.0 {
+            let new_y = transform.translation.y + player.speed * movement.y;
+            if valid_move(transform.translation.x, new_y, player_size) {
                 transform.translation.y = new_y;
             }
         }
 
-        if keyboard_input.pressed(KeyCode::S) {
-            let new_y =
-                transform.translation.y - player.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
-            if valid_move(transform.translation.x, new_y, window, player_size) {
-                transform.translation.y = new_y;
-            }
-        }
-
-        if keyboard_input.pressed(KeyCode::A) {
-            let new_x =
-                transform.translation.x - player.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
-            if valid_move(new_x, transform.translation.y, window, player_size) {
-                transform.translation.x = new_x;
-            }
-        }
-
-        if keyboard_input.pressed(KeyCode::D) {
-            let new_x =
-                transform.translation.x + player.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
-            if valid_move(new_x, transform.translation.y, window, player_size) {
+        if movement.x != 0.0 {
+            let new_x = transform.translation.x + player.speed * movement.x;
+            if valid_move(new_x, transform.translation.y, player_size) {
                 transform.translation.x = new_x;
             }
         }
     }
 }
 
-fn valid_move(x: f32, y: f32, window: &Window, player_size: Vec2) -> bool {
-    let min_x = -(window.width() / 2.) + (player_size.x / 2.);
-    let max_x = (window.width() / 2.) - (player_size.x / 2.);
+fn valid_move(x: f32, y: f32, player_size: Vec2) -> bool {
+    let min_x = -(PLAYFIELD.x / 2.) + (player_size.x / 2.);
+    let max_x = (PLAYFIELD.x / 2.) - (player_size.x / 2.);
 
-    let min_y = -(window.height() / 2.) + (player_size.y / 2.);
-    let max_y = (window.height() / 2.) - (player_size.y / 2.);
+    let min_y = -(PLAYFIELD.y / 2.) + (player_size.y / 2.);
+    let max_y = (PLAYFIELD.y / 2.) - (player_size.y / 2.);
     x >= min_x && x <= max_x && y >= min_y && y <= max_y
 }
 
-fn player_border(
-    mut player_query: Query<(&mut Player, &mut Transform, &Handle<Image>)>,
-    assets: Res<Assets<Image>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-) {
-    let window = window_query.single();
-    let (_player, mut pos, img) = player_query.single_mut();
-    let player_size = assets.get(img).unwrap().size();
+fn player_border(mut player_query: Query<(&mut Player, &mut Transform, &Collider)>) {
+    for (_player, mut pos, collider) in &mut player_query {
+        let player_size = collider.size;
+
+        let max_y = PLAYFIELD.y / 2.0 - player_size.y / 2.0;
+        let min_y = -PLAYFIELD.y / 2.0 + player_size.y / 2.0;
 
-    let max_y = window.height() / 2.0 - player_size.y / 2.0;
-    let min_y = -window.height() / 2.0 + player_size.y / 2.0;
+        if pos.translation.y > max_y {
+            pos.translation.y = max_y;
+        }
+
+        if pos.translation.y < min_y {
+            pos.translation.y = min_y;
+        }
 
-    if pos.translation.y > max_y {
-        pos.translation.y = max_y;
+        let max_x = PLAYFIELD.x / 2.0 - player_size.x / 2.0;
+        let min_x = -PLAYFIELD.x / 2.0 + player_size.x / 2.0;
+
+        if pos.translation.x > max_x {
+            pos.translation.x = max_x;
+        }
+
+        if pos.translation.x < min_x {
+            pos.translation.x = min_x;
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
 
-    if pos.translation.y < min_y {
-        pos.translation.y = min_y;
+    use crate::{
+        playfield::PLAYFIELD,
+        test_harness::{TestGame, PLAYER_SIZE},
+    };
+
+    #[test]
+    fn player_moves_with_keys() {
+        let mut game = TestGame::new();
+        let player = game.player();
+
+        game.press(KeyCode::D);
+        game.press(KeyCode::W);
+        game.step_frames(10);
+
+        let position = game.position(player);
+        assert!((position.x - 56.18).abs() < 0.01);
+        assert!((position.y - 56.18).abs() < 0.01);
     }
 
-    let max_x = window.width() / 2.0 - player_size.x / 2.0;
-    let min_x = -window.width() / 2.0 + player_size.x / 2.0;
+    #[test]
+    fn player_stays_on_screen() {
+        let mut game = TestGame::new();
+        let player = game.player();
 
-    if pos.translation.x > max_x {
-        pos.translation.x = max_x;
+        game.press(KeyCode::A);
+        game.step_frames(200);
+
+        let min_x = -PLAYFIELD.x / 2.0 + PLAYER_SIZE.x / 2.0;
+        let x = game.position(player).x;
+        assert!(x >= min_x && x < min_x + 5.618);
     }
 
-    if pos.translation.x < min_x {
-        pos.translation.x = min_x;
+    #[test]
+    fn player_can_move_along_the_border() {
+        let mut game = TestGame::new();
+        let player = game.player();
+
+        game.press(KeyCode::A);
+        game.step_frames(200);
+        game.press(KeyCode::W);
+        game.step_frames(10);
+
+        assert!((game.position(player).y - 56.18).abs() < 0.01);
     }
 }
@@ -0,0 +1,55 @@
+//! Gameplay happens on a fixed playfield centered on the origin, whatever the size of the window.
+//! The camera scales it to fit the window and black bars cover what's shown beyond it.
+
+use bevy::{prelude::*, render::camera::ScalingMode};
+
+use crate::Layers;
+
+/// Size of the original game, in gameplay pixels.
+pub const PLAYFIELD: Vec2 = Vec2::new(1120.0, 605.0);
+
+/// Large enough to cover the rest of any window.
+const BAR_SIZE: f32 = 100_000.0;
+
+pub struct PlayfieldPlugin;
+
+impl Plugin for PlayfieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ClearColor(Color::BLACK))
+            .add_startup_system(setup_camera);
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle {
+        projection: OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: PLAYFIELD.x,
+                min_height: PLAYFIELD.y,
+            },
+            ..default()
+        },
+        ..default()
+    });
+
+    // Sprites spawning or leaving beyond the edges shouldn't be visible in the extra space.
+    let bars = [
+        Vec2::new(-1.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(0.0, -1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+    for side in bars {
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::BLACK,
+                custom_size: Some(Vec2::splat(BAR_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(
+                (side * (PLAYFIELD + BAR_SIZE) / 2.0).extend(Layers::UI.order_nr()),
+            ),
+            ..default()
+        });
+    }
+}
@@ -0,0 +1,273 @@
+//! Mouse and touch controls, mostly for the wasm build on tablets and phones.
+//! The ship glides toward the pointer, clicks and touches fire and touch devices get buttons to pick a weapon.
+
+use bevy::{input::touch::TouchInput, prelude::*, ui::UiSystem, window::PrimaryWindow};
+
+use crate::{
+    controls::{collect_actions, Action, ActionState, ActionSystem},
+    netplay::{local_player, NetSession},
+    replay::ReplaySystem,
+    settings::Settings,
+    simulation::Interpolated,
+    AppState, Player,
+};
+
+const SELECTOR_BUTTON_SIZE: f32 = 64.0;
+const SELECTOR_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+const SELECTED_COLOR: Color = Color::rgba(1.0, 0.8, 0.3, 0.5);
+const LOCKED_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.3);
+
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            pointer_actions
+                .in_base_set(CoreSet::PreUpdate)
+                .in_set(ActionSystem)
+                .after(collect_actions)
+                .after(UiSystem::Focus)
+                .before(ReplaySystem)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_system(setup_weapon_selector.in_schedule(OnExit(AppState::Loading)))
+        .add_system(show_weapon_selector.in_set(OnUpdate(AppState::InGame)))
+        .add_system(hide_weapon_selector.in_schedule(OnExit(AppState::InGame)));
+    }
+}
+
+/// Picks the weapon in its slot when touched.
+#[derive(Component)]
+struct WeaponButton(usize);
+
+#[derive(Component)]
+struct WeaponSelector;
+
+#[allow(clippy::too_many_arguments)]
+fn pointer_actions(
+    settings: Res<Settings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    touches: Res<Touches>,
+    player_query: Query<(&Player, &Interpolated)>,
+    session: Option<Res<NetSession>>,
+    button_query: Query<(&Interaction, &WeaponButton)>,
+    mut actions: ResMut<ActionState>,
+) {
+    let mouse_moved = cursor_moved.iter().last().is_some();
+
+    // Pointers on the weapon selector don't steer or fire.
+    let mut on_selector = false;
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked {
+            actions.just_press(Action::WEAPONS[button.0]);
+        }
+        on_selector |= *interaction != Interaction::None;
+    }
+    if on_selector {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    // Touches start at the top of the window, the cursor at the bottom.
+    let touch = touches.iter().next();
+    let touch_position =
+        touch.map(|touch| Vec2::new(touch.position().x, window.height() - touch.position().y));
+    let (pressed, just_pressed) = if touch.is_some() {
+        (true, touches.any_just_pressed())
+    } else {
+        (
+            mouse_input.pressed(MouseButton::Left),
+            mouse_input.just_pressed(MouseButton::Left),
+        )
+    };
+    let cursor_position = window
+        .cursor_position()
+        .filter(|_| settings.pointer_movement);
+    let mouse_active = pressed || mouse_moved;
+    let position = steering_position(
+        actions.movement,
+        touch_position,
+        cursor_position,
+        mouse_active,
+    );
+
+    if pressed {
+        actions.press(Action::Fire);
+    }
+    if just_pressed {
+        actions.just_press(Action::Fire);
+    }
+
+    let target =
+        position.and_then(|position| camera.viewport_to_world_2d(camera_transform, position));
+    // Pointers steer the player on this machine, like the keys bound in the settings.
+    let index = local_player(session.as_deref());
+    let local_player = player_query
+        .iter()
+        .find(|(player, _interpolated)| player.index == index);
+    if let (Some(target), Some((player, interpolated))) = (target, local_player) {
+        actions.movement = steer(interpolated.current().truncate(), target, player.speed);
+    }
+}
+
+/// Where the pointer steers the ship to, if anywhere. Touches always steer, the mouse only
+/// while it's held or moving, so a resting one doesn't keep pulling the ship back.
+/// Keys and sticks win over both.
+fn steering_position(
+    movement: Vec2,
+    touch: Option<Vec2>,
+    cursor: Option<Vec2>,
+    mouse_active: bool,
+) -> Option<Vec2> {
+    if movement != Vec2::ZERO {
+        return None;
+    }
+    touch.or(cursor.filter(|_| mouse_active))
+}
+
+/// Full speed until the ship is less than a tick away from the target, then just what's left.
+fn steer(position: Vec2, target: Vec2, speed: f32) -> Vec2 {
+    ((target - position) / speed).clamp_length_max(1.0)
+}
+
+fn setup_weapon_selector(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/impact.ttf"),
+        font_size: 39.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(12.0),
+                        bottom: Val::Px(60.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            WeaponSelector,
+        ))
+        .with_children(|parent| {
+            for slot in 0..Action::WEAPONS.len() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Px(SELECTOR_BUTTON_SIZE),
+                                    Val::Px(SELECTOR_BUTTON_SIZE),
+                                ),
+                                margin: UiRect::top(Val::Px(6.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: SELECTOR_COLOR.into(),
+                            ..default()
+                        },
+                        WeaponButton(slot),
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            (slot + 1).to_string(),
+                            text_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+/// Only touch devices get the selector, the others have keys or buttons for the weapons.
+fn show_weapon_selector(
+    mut ev_touch: EventReader<TouchInput>,
+    mut touched: Local<bool>,
+    player_query: Query<&Player>,
+    session: Option<Res<NetSession>>,
+    mut selector_query: Query<&mut Visibility, With<WeaponSelector>>,
+    mut button_query: Query<(&WeaponButton, &mut BackgroundColor)>,
+) {
+    *touched |= ev_touch.iter().count() > 0;
+    if !*touched {
+        return;
+    }
+
+    for mut visibility in &mut selector_query {
+        *visibility = Visibility::Inherited;
+    }
+
+    let index = local_player(session.as_deref());
+    let Some(player) = player_query.iter().find(|player| player.index == index) else {
+        return;
+    };
+    for (button, mut color) in &mut button_query {
+        *color = if button.0 == player.current_weapon_index {
+            SELECTED_COLOR
+        } else if button.0 >= player.level as usize {
+            LOCKED_COLOR
+        } else {
+            SELECTOR_COLOR
+        }
+        .into();
+    }
+}
+
+fn hide_weapon_selector(mut selector_query: Query<&mut Visibility, With<WeaponSelector>>) {
+    for mut visibility in &mut selector_query {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{steer, steering_position};
+
+    #[test]
+    fn ship_glides_toward_the_pointer_at_full_speed() {
+        let movement = steer(Vec2::ZERO, Vec2::new(300.0, 400.0), 5.0);
+
+        assert!((movement - Vec2::new(0.6, 0.8)).length() < 0.001);
+    }
+
+    #[test]
+    fn ship_stops_on_the_pointer() {
+        let movement = steer(Vec2::new(10.0, 10.0), Vec2::new(12.5, 10.0), 5.0);
+
+        assert!((movement - Vec2::new(0.5, 0.0)).length() < 0.001);
+        assert_eq!(steer(Vec2::ONE, Vec2::ONE, 5.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn movement_keys_win_over_the_cursor() {
+        let cursor = Some(Vec2::new(300.0, 400.0));
+
+        assert_eq!(steering_position(Vec2::X, None, cursor, true), None);
+        assert_eq!(steering_position(Vec2::ZERO, None, cursor, true), cursor);
+    }
+
+    #[test]
+    fn resting_mouse_does_not_steer() {
+        let cursor = Some(Vec2::new(300.0, 400.0));
+        let touch = Some(Vec2::new(10.0, 20.0));
+
+        assert_eq!(steering_position(Vec2::ZERO, None, cursor, false), None);
+        assert_eq!(steering_position(Vec2::ZERO, touch, cursor, false), touch);
+    }
+}
@@ -0,0 +1,549 @@
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+    utils::Instant,
+};
+
+use crate::{
+    controls::{ActionState, ActionSystem, SecondPlayerActions},
+    coop::GameMode,
+    launch_options::launch_option,
+    netplay::NetSession,
+    rng::GameRng,
+    settings::Settings,
+    simulation::TickInputSystem,
+    AppState,
+};
+
+const MAGIC: &[u8; 4] = b"SSPR";
+const VERSION: u8 = 5;
+const HEADER_LEN: usize = 4 + 1 + 8 + 1 + 1;
+/// Records the actions of the players into a replay file (`--record <file>`)
+/// or plays one back instead of the keyboard and gamepads (`--replay <file>`).
+pub enum ReplayPlugin {
+    Off,
+    Record(PathBuf),
+    Replay(Replay),
+}
+
+impl ReplayPlugin {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_launch_options() -> Self {
+        if let Some(path) = launch_option("replay") {
+            let replay = Replay::load(&path)
+                .unwrap_or_else(|err| panic!("Could not read replay `{path}`: {err}"));
+            Self::Replay(replay)
+        } else if let Some(path) = launch_option("record") {
+            Self::Record(path.into())
+        } else {
+            Self::Off
+        }
+    }
+
+    /// Browsers have no files to record into or play back from.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_launch_options() -> Self {
+        if launch_option("replay").is_some() || launch_option("record").is_some() {
+            warn!("Replays are only supported by native builds");
+        }
+        Self::Off
+    }
+}
+
+/// Records or plays back the actions, systems changing the actions have to run before.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ReplaySystem;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            ReplayPlugin::Off => {}
+            ReplayPlugin::Record(path) => {
+                let seed = app.world.resource::<GameRng>().seed;
+
+                app.insert_resource(ReplayRecorder {
+                    path: path.clone(),
+                    replay: Replay {
+                        seed,
+                        hold_to_fire: true,
+                        mode: GameMode::Solo,
+                        frames: Vec::new(),
+                    },
+                    started: false,
+                    ended: false,
+                })
+                // Online games get their inputs from the network, so only games without are recorded.
+                .add_system(
+                    record_frame_time
+                        .in_base_set(CoreSet::First)
+                        .before(TimeSystem)
+                        .run_if(not(resource_exists::<NetSession>())),
+                )
+                .add_system(
+                    record_actions
+                        .in_base_set(CoreSet::PreUpdate)
+                        .in_set(ReplaySystem)
+                        .after(ActionSystem)
+                        .before(TickInputSystem)
+                        .run_if(not(resource_exists::<NetSession>())),
+                )
+                .add_system(save_replay.in_schedule(OnEnter(AppState::GameOver)))
+                .add_system(save_replay.in_schedule(OnEnter(AppState::GameWon)))
+                .add_system(save_replay_on_exit.in_base_set(CoreSet::Last));
+            }
+            ReplayPlugin::Replay(replay) => {
+                // Same seed, same enemies.
+                app.world
+                    .get_resource_or_insert_with(Settings::default)
+                    .hold_to_fire_override = Some(replay.hold_to_fire);
+                app.insert_resource(GameRng::new(replay.seed))
+                    .insert_resource(replay.mode)
+                    .insert_resource(ReplayPlayer {
+                        replay: replay.clone(),
+                        frame: 0,
+                        started: false,
+                    })
+                    .add_system(skip_menu.in_schedule(OnEnter(AppState::MainMenu)))
+                    .add_system(
+                        replay_frame_time
+                            .in_base_set(CoreSet::First)
+                            .before(TimeSystem),
+                    )
+                    .add_system(
+                        replay_actions
+                            .in_base_set(CoreSet::PreUpdate)
+                            .in_set(ReplaySystem)
+                            .after(ActionSystem)
+                            .before(TickInputSystem),
+                    );
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub seed: u64,
+    /// Setting of the recording, it changes how the fire input is handled.
+    pub hold_to_fire: bool,
+    pub mode: GameMode,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Only the actions of the players in the game are stored, so solo replays stay small.
+    fn frame_len(mode: GameMode) -> usize {
+        4 + mode.players() * ReplayActions::LEN
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(HEADER_LEN + self.frames.len() * Self::frame_len(self.mode));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.hold_to_fire as u8);
+        bytes.push(self.mode.players() as u8);
+
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.delta.to_le_bytes());
+            for actions in &frame.players[..self.mode.players()] {
+                actions.write(&mut bytes);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported replay version"));
+        }
+        let mode = match bytes[14] {
+            1 => GameMode::Solo,
+            2 => GameMode::Coop,
+            _ => return Err(invalid("unsupported number of players")),
+        };
+        let frame_len = Self::frame_len(mode);
+        if !(bytes.len() - HEADER_LEN).is_multiple_of(frame_len) {
+            return Err(invalid("replay file is truncated"));
+        }
+
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let hold_to_fire = bytes[13] != 0;
+        let frames = bytes[HEADER_LEN..]
+            .chunks_exact(frame_len)
+            .map(|frame| {
+                let mut players = [ReplayActions::default(); 2];
+                for (actions, bytes) in players
+                    .iter_mut()
+                    .zip(frame[4..].chunks_exact(ReplayActions::LEN))
+                {
+                    *actions = ReplayActions::read(bytes);
+                }
+                ReplayFrame {
+                    delta: u32::from_le_bytes(frame[0..4].try_into().unwrap()),
+                    players,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            seed,
+            hold_to_fire,
+            mode,
+            frames,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ReplayFrame {
+    /// Frame time in microseconds.
+    pub delta: u32,
+    /// Actions of both players, the second one is only used in co-op.
+    pub players: [ReplayActions; 2],
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ReplayActions {
+    /// Both movement axes, scaled to the whole range of `i16`.
+    pub movement: [i16; 2],
+    /// Bits of `Action`s, like in `ActionState`.
+    pub pressed: u32,
+    pub just_pressed: u32,
+}
+
+impl ReplayActions {
+    pub const LEN: usize = 2 * 2 + 4 + 4;
+
+    /// Appends `LEN` bytes, also used to send the actions in netplay.
+    pub fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.movement[0].to_le_bytes());
+        bytes.extend_from_slice(&self.movement[1].to_le_bytes());
+        bytes.extend_from_slice(&self.pressed.to_le_bytes());
+        bytes.extend_from_slice(&self.just_pressed.to_le_bytes());
+    }
+
+    /// Reads what `write` wrote, `bytes` has to be `LEN` long.
+    pub fn read(bytes: &[u8]) -> Self {
+        Self {
+            movement: [
+                i16::from_le_bytes(bytes[0..2].try_into().unwrap()),
+                i16::from_le_bytes(bytes[2..4].try_into().unwrap()),
+            ],
+            pressed: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            just_pressed: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+
+    pub fn capture_actions(&mut self, actions: &ActionState) {
+        let quantize = |axis: f32| (axis.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+
+        self.movement = [quantize(actions.movement.x), quantize(actions.movement.y)];
+        self.pressed = actions.pressed;
+        self.just_pressed = actions.just_pressed;
+    }
+
+    pub fn apply_actions(&self, actions: &mut ActionState) {
+        actions.movement = Vec2::new(
+            self.movement[0] as f32 / i16::MAX as f32,
+            self.movement[1] as f32 / i16::MAX as f32,
+        );
+        actions.pressed = self.pressed;
+        actions.just_pressed = self.just_pressed;
+    }
+}
+
+#[derive(Resource)]
+struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+    started: bool,
+    /// Settings stored once for the whole replay changed, so the rest can't be played back.
+    ended: bool,
+}
+
+/// Only exists while a replay is played back.
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    frame: usize,
+    started: bool,
+}
+
+/// Frames are only recorded from the first one that runs the game, since loading takes a different time on every run.
+fn gameplay_starts(state: &State<AppState>, next_state: &NextState<AppState>) -> bool {
+    state.0 == AppState::InGame || next_state.0 == Some(AppState::InGame)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_frame_time(
+    time: Res<Time>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    rng: Res<GameRng>,
+    state: Res<State<AppState>>,
+    next_state: Res<NextState<AppState>>,
+) {
+    if recorder.ended || (!recorder.started && !gameplay_starts(&state, &next_state)) {
+        return;
+    }
+    if !recorder.started {
+        // Online games before this one changed the seed.
+        recorder.replay.seed = rng.seed;
+        recorder.replay.hold_to_fire = settings.hold_to_fire();
+        recorder.replay.mode = *mode;
+        recorder.started = true;
+    } else if settings.hold_to_fire() != recorder.replay.hold_to_fire
+        || *mode != recorder.replay.mode
+    {
+        info!("Hold to fire or the number of players changed, ending the replay");
+        recorder.ended = true;
+        save_replay(recorder.into());
+        return;
+    }
+
+    // Round the frame time to whole microseconds, so the replay gets exactly the same times.
+    let last_update = time.last_update().unwrap_or_else(|| time.startup());
+    let delta = (Instant::now() - last_update).as_micros() as u32;
+
+    recorder
+        .replay
+        .frames
+        .push(ReplayFrame { delta, ..default() });
+    *time_update_strategy =
+        TimeUpdateStrategy::ManualInstant(last_update + Duration::from_micros(delta as u64));
+}
+
+fn record_actions(
+    mut actions: ResMut<ActionState>,
+    mut second_actions: ResMut<SecondPlayerActions>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if recorder.ended {
+        return;
+    }
+    if let Some(frame) = recorder.replay.frames.last_mut() {
+        for (recorded, actions) in frame
+            .players
+            .iter_mut()
+            .zip([&mut *actions, &mut second_actions.0])
+        {
+            recorded.capture_actions(actions);
+            // Play with the rounded movement, so the replay gets exactly the same.
+            recorded.apply_actions(actions);
+        }
+    }
+}
+
+fn save_replay(recorder: Res<ReplayRecorder>) {
+    if !recorder.started {
+        return;
+    }
+    match fs::write(&recorder.path, recorder.replay.to_bytes()) {
+        Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+        Err(err) => error!(
+            "Could not save replay to {}: {err}",
+            recorder.path.display()
+        ),
+    }
+}
+
+fn save_replay_on_exit(ev_exit: EventReader<AppExit>, recorder: Res<ReplayRecorder>) {
+    if !ev_exit.is_empty() {
+        save_replay(recorder);
+    }
+}
+
+fn replay_frame_time(
+    time: Res<Time>,
+    mut player: ResMut<ReplayPlayer>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    state: Res<State<AppState>>,
+    next_state: Res<NextState<AppState>>,
+) {
+    if !player.started && !gameplay_starts(&state, &next_state) {
+        return;
+    }
+    player.started = true;
+
+    *time_update_strategy = match player.replay.frames.get(player.frame) {
+        Some(frame) => {
+            let last_update = time.last_update().unwrap_or_else(|| time.startup());
+            TimeUpdateStrategy::ManualInstant(
+                last_update + Duration::from_micros(frame.delta as u64),
+            )
+        }
+        None => TimeUpdateStrategy::Automatic,
+    };
+}
+
+/// Replays start with the game itself, like their recording did.
+fn skip_menu(player: Res<ReplayPlayer>, mut next_state: ResMut<NextState<AppState>>) {
+    if !player.started {
+        next_state.set(AppState::InGame);
+    }
+}
+
+fn replay_actions(
+    mut actions: ResMut<ActionState>,
+    mut second_actions: ResMut<SecondPlayerActions>,
+    mut player: ResMut<ReplayPlayer>,
+) {
+    if !player.started {
+        return;
+    }
+
+    if let Some(frame) = player.replay.frames.get(player.frame) {
+        frame.players[0].apply_actions(&mut actions);
+        frame.players[1].apply_actions(&mut second_actions);
+    } else if player.frame == player.replay.frames.len() {
+        info!("Replay finished, the keyboard and gamepads are back in control.");
+    }
+    player.frame += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{Replay, ReplayActions, ReplayFrame, ReplayPlayer};
+    use crate::{
+        controls::{Action, ActionState},
+        coop::GameMode,
+        high_scores::{HighScorePlugin, HighScores, NameEntry},
+        test_harness::TestGame,
+        AppState,
+    };
+
+    #[test]
+    fn replay_survives_a_round_trip_through_bytes() {
+        let replay = Replay {
+            seed: 42,
+            hold_to_fire: false,
+            mode: GameMode::Coop,
+            frames: vec![
+                ReplayFrame {
+                    delta: 16_667,
+                    players: [
+                        ReplayActions {
+                            movement: [i16::MAX, -12_000],
+                            pressed: 0b1001,
+                            just_pressed: 0b1000,
+                        },
+                        ReplayActions {
+                            movement: [-300, 0],
+                            pressed: 0b10000,
+                            just_pressed: 0,
+                        },
+                    ],
+                },
+                ReplayFrame {
+                    delta: 16_940,
+                    players: [
+                        ReplayActions {
+                            movement: [0, i16::MIN + 1],
+                            pressed: 0,
+                            just_pressed: 0b10000,
+                        },
+                        ReplayActions::default(),
+                    ],
+                },
+            ],
+        };
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        assert_eq!(loaded.seed, 42);
+        assert!(!loaded.hold_to_fire);
+        assert_eq!(loaded.mode, GameMode::Coop);
+        assert_eq!(loaded.frames, replay.frames);
+    }
+
+    #[test]
+    fn solo_replay_only_stores_the_first_player() {
+        let mut frame = ReplayFrame::default();
+        frame.players[1].pressed = 0b1;
+        let replay = Replay {
+            seed: 7,
+            hold_to_fire: true,
+            mode: GameMode::Solo,
+            frames: vec![frame],
+        };
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        assert_eq!(loaded.mode, GameMode::Solo);
+        assert_eq!(loaded.frames, vec![ReplayFrame::default()]);
+    }
+
+    #[test]
+    fn invalid_replay_is_rejected() {
+        assert!(Replay::from_bytes(b"not a replay").is_err());
+    }
+
+    #[test]
+    fn replayed_actions_match_recorded_actions() {
+        let mut recorded = ActionState {
+            movement: Vec2::new(0.3, -1.0),
+            pressed: 0b1_0001,
+            just_pressed: 0b1,
+        };
+
+        let mut frame = ReplayActions::default();
+        frame.capture_actions(&recorded);
+        frame.apply_actions(&mut recorded);
+
+        let mut replayed = ActionState::default();
+        frame.apply_actions(&mut replayed);
+
+        assert_eq!(replayed, recorded);
+        assert!((replayed.movement - Vec2::new(0.3, -1.0)).length() < 0.001);
+        assert!(replayed.pressed(Action::Fire) && !replayed.just_pressed(Action::Fire));
+    }
+
+    #[test]
+    fn watching_a_replay_does_not_add_a_high_score() {
+        let mut game = TestGame::new();
+        game.app
+            .add_event::<ReceivedCharacter>()
+            .add_plugin(HighScorePlugin)
+            .insert_resource(HighScores::default())
+            .insert_resource(ReplayPlayer {
+                replay: Replay {
+                    seed: 0,
+                    hold_to_fire: true,
+                    mode: GameMode::Solo,
+                    frames: Vec::new(),
+                },
+                frame: 0,
+                started: true,
+            });
+
+        game.player_state_mut().score = 1000;
+        game.game_mut().earth_health = 0;
+        game.step_frames(2);
+        assert_eq!(game.state(), AppState::GameOver);
+        assert!(game.app.world.get_resource::<NameEntry>().is_none());
+
+        game.tap(KeyCode::Return);
+        game.step();
+
+        assert!(game.app.world.resource::<HighScores>().entries.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::launch_options::launch_option;
+
+/// Source of all randomness in the game, so that a run can be reproduced from its seed.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    /// Everything that influences gameplay, like which enemies spawn and how fast they are.
+    pub gameplay: ChaCha8Rng,
+    /// Purely visual things like stars and planets.
+    /// Kept apart so they can't change the gameplay, no matter in which order systems run.
+    pub backdrop: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let mut backdrop = ChaCha8Rng::seed_from_u64(seed);
+        backdrop.set_stream(1);
+
+        Self {
+            seed,
+            gameplay: ChaCha8Rng::seed_from_u64(seed),
+            backdrop,
+        }
+    }
+
+    /// Uses the seed given with `--seed` (native) or `?seed=` (wasm), a random one otherwise.
+    pub fn from_launch_options() -> Self {
+        let seed = launch_option("seed")
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(rand::random);
+
+        Self::new(seed)
+    }
+}
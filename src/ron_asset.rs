@@ -0,0 +1,82 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{Asset, AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// Game data (weapons, enemies, ...) that designers author as RON files in `assets/data`.
+pub trait RonAsset: Asset + DeserializeOwned {
+    /// Compound extensions handled by the loader, e.g. `weapons.ron`.
+    const EXTENSIONS: &'static [&'static str];
+
+    /// Turns asset paths referenced in the file (like sprites) into handles.
+    /// Returns the paths so they get loaded as dependencies.
+    fn resolve_dependencies(&mut self, _load_context: &LoadContext) -> Vec<AssetPath<'static>> {
+        Vec::new()
+    }
+
+    /// Rejects values that would only cause trouble once the game uses them.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub struct RonAssetPlugin<A: RonAsset> {
+    _marker: PhantomData<A>,
+}
+
+impl<A: RonAsset> Default for RonAssetPlugin<A> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: RonAsset> Plugin for RonAssetPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<A>().add_asset_loader(RonAssetLoader::<A> {
+            _marker: PhantomData,
+        });
+    }
+}
+
+struct RonAssetLoader<A: RonAsset> {
+    _marker: PhantomData<A>,
+}
+
+impl<A: RonAsset> AssetLoader for RonAssetLoader<A> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let mut asset = ron::de::from_bytes::<A>(bytes)?;
+            asset.validate().map_err(bevy::asset::Error::msg)?;
+            let dependencies = asset.resolve_dependencies(load_context);
+
+            load_context.set_default_asset(LoadedAsset::new(asset).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        A::EXTENSIONS
+    }
+}
+
+/// Resolves a path from a data file into a handle, remembering it as a dependency.
+pub fn resolve_handle<T: Asset>(
+    path: &str,
+    load_context: &LoadContext,
+    dependencies: &mut Vec<AssetPath<'static>>,
+) -> Handle<T> {
+    let asset_path = AssetPath::from(path).to_owned();
+    let handle = load_context.get_handle(asset_path.clone());
+    dependencies.push(asset_path);
+    handle
+}
@@ -0,0 +1,77 @@
+//! Preferences of the player, saved in `settings.ron` (native) or the `localStorage` of the browser (wasm).
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const SETTINGS_FILE: &str = "settings";
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Weapons with `auto_fire` keep shooting while fire is held.
+    pub hold_to_fire: bool,
+    /// The ship follows the mouse, touches always steer it.
+    pub pointer_movement: bool,
+    /// Replays play with the `hold_to_fire` of their recording, without changing the saved one.
+    #[serde(skip)]
+    pub hold_to_fire_override: Option<bool>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hold_to_fire: true,
+            pointer_movement: cfg!(target_arch = "wasm32"),
+            hold_to_fire_override: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_FILE)
+            .map(|ron| Self::from_ron(&ron))
+            .unwrap_or_default()
+    }
+
+    /// What firing plays with right now, see `hold_to_fire_override`.
+    pub fn hold_to_fire(&self) -> bool {
+        self.hold_to_fire_override.unwrap_or(self.hold_to_fire)
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_FILE, &self.to_ron());
+    }
+
+    fn from_ron(ron: &str) -> Self {
+        ron::from_str(ron).unwrap_or_else(|err| {
+            warn!("Ignoring broken settings: {err}");
+            Self::default()
+        })
+    }
+
+    fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+
+    #[test]
+    fn overrides_are_not_saved() {
+        let settings = Settings {
+            hold_to_fire: true,
+            hold_to_fire_override: Some(false),
+            ..Settings::default()
+        };
+
+        assert!(!settings.hold_to_fire());
+        let loaded = Settings::from_ron(&settings.to_ron());
+        assert!(loaded.hold_to_fire());
+        assert_eq!(loaded.hold_to_fire_override, None);
+    }
+}
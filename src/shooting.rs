@@ -1,391 +1,380 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use bevy::{prelude::*, sprite::collide_aabb::collide, window::PrimaryWindow};
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
 
 use crate::{
-    AnimationIndices, AnimationTimer, AppState, Enemy, Game, Layers, MyAssets, Player,
-    ORIGINAL_TARGET_FPS,
+    boss::HitZones,
+    collision::{Collider, CollisionGrid, CollisionGridSystem, CollisionMasks},
+    controls::Action,
+    lives::{Respawning, Vulnerable},
+    playfield::PLAYFIELD,
+    ron_asset::{resolve_handle, RonAsset, RonAssetPlugin},
+    settings::Settings,
+    simulation::{Interpolated, TickInput, TickSet},
+    AnimationIndices, AnimationTimer, Enemy, EnemyDestroyedEvent, Layers, MyAssets, Player,
 };
 
-const PLAYER_WIDTH: f32 = 49.5;
-const PLAYER_WING_TIPS: f32 = 25.0;
-
 pub struct ShootingPlugin;
 
 impl Plugin for ShootingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<WeaponSwitchedEvent>().add_systems(
-            (
-                player_shoot,
-                enemy_shoot,
-                projectile_move,
-                projectile_collision,
-                weapon_switching,
+        app.add_plugin(RonAssetPlugin::<WeaponCatalog>::default())
+            .add_event::<WeaponSwitchedEvent>()
+            .add_systems(
+                (
+                    player_shoot,
+                    enemy_shoot,
+                    projectile_move,
+                    projectile_collision.after(CollisionGridSystem),
+                    weapon_switching,
+                )
+                    .in_set(TickSet::Gameplay)
+                    .in_schedule(CoreSchedule::FixedUpdate),
             )
-                .in_set(OnUpdate(AppState::InGame)),
-        );
+            .add_system(reload_weapons);
     }
 }
 
-#[derive(Clone, Copy)]
-pub enum ProjectileType {
-    Blaster,
-    Grim,
-    Hammer,
-    Ratata,
-    Stomp,
+#[derive(Deserialize, Clone, Copy)]
+pub enum HitEffect {
+    Red,
+    Blue,
+    Green,
+}
+
+impl HitEffect {
+    pub fn texture_atlas(&self, assets: &Res<MyAssets>) -> Handle<TextureAtlas> {
+        match self {
+            HitEffect::Red => assets.hit_red.clone(),
+            HitEffect::Blue => assets.hit_blue.clone(),
+            HitEffect::Green => assets.hit_green.clone(),
+        }
+    }
 }
 
+/// The player who fired a projectile, gets the score for what it destroys.
+#[derive(Component, Clone, Copy)]
+pub struct FiredBy(pub Entity);
+
 #[derive(Component, Clone, Copy)]
 pub struct Projectile {
     pub speed: f32,
     pub damage: u32,
     pub friendly: bool,
-    pub projectile_type: ProjectileType,
+    pub hit_effect: HitEffect,
     pub pushback: f32,
 }
 
-impl Projectile {
-    pub fn image(&self, assets: &Res<MyAssets>) -> Handle<Image> {
-        match self.projectile_type {
-            ProjectileType::Blaster => assets.blaster.clone(),
-            ProjectileType::Grim => assets.grim.clone(),
-            ProjectileType::Hammer => assets.hammer.clone(),
-            ProjectileType::Ratata => assets.ratata.clone(),
-            ProjectileType::Stomp => assets.stomp.clone(),
-        }
-    }
+/// All weapons of the game, loaded from `assets/data/arsenal.weapons.ron`.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "92169491-1eee-4b57-b792-ab3b97e8514f"]
+pub struct WeaponCatalog {
+    /// Keys of the weapons in the player's slots.
+    pub player_loadout: Vec<String>,
+    pub weapons: HashMap<String, WeaponDefinition>,
 }
 
-#[derive(Component, Clone)]
-pub struct Weapon {
-    pub name: String,
-    pub cooldown_timer: Timer,
-    pub gun_positions: Vec<Transform>,
-    pub mounting_point: Transform,
-    pub projectile: Projectile,
-}
-
-impl Weapon {
-    pub fn stomp(friendly: bool) -> Self {
-        let mut cooldown = 5.0 / ORIGINAL_TARGET_FPS;
-        if !friendly {
-            cooldown *= 2.0
-        }
-        let mut timer = Timer::new(Duration::from_secs_f32(cooldown), TimerMode::Once);
-        timer.pause();
-
-        Weapon {
-            name: String::from("Stomp O´ Matic"),
-            cooldown_timer: timer,
-            mounting_point: if friendly {
-                Transform::from_xyz(-PLAYER_WIDTH, -PLAYER_WING_TIPS, 0.0)
-            } else {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            },
-            gun_positions: vec![Transform::from_xyz(0.0, 0.0, 0.0)],
-            projectile: if friendly {
-                Projectile {
-                    friendly: true,
-                    speed: 13.0,
-                    damage: 6,
-                    projectile_type: ProjectileType::Stomp,
-                    pushback: 0.0,
-                }
-            } else {
-                Projectile {
-                    friendly: false,
-                    speed: 10.0,
-                    damage: 6,
-                    projectile_type: ProjectileType::Stomp,
-                    pushback: 0.0,
-                }
-            },
+impl RonAsset for WeaponCatalog {
+    const EXTENSIONS: &'static [&'static str] = &["weapons.ron"];
+
+    fn resolve_dependencies(&mut self, load_context: &LoadContext) -> Vec<AssetPath<'static>> {
+        let mut dependencies = Vec::new();
+        for definition in self.weapons.values_mut() {
+            definition.projectile_image = resolve_handle(
+                &definition.projectile_sprite,
+                load_context,
+                &mut dependencies,
+            );
         }
+        dependencies
     }
 
-    pub fn blaster(friendly: bool) -> Self {
-        let mut cooldown = 2300.0 / 40.0 / ORIGINAL_TARGET_FPS;
-        if !friendly {
-            cooldown *= 2.0
+    fn validate(&self) -> Result<(), String> {
+        if self.player_loadout.is_empty() {
+            return Err("The player loadout needs at least one weapon.".to_string());
         }
-
-        let mut timer = Timer::new(Duration::from_secs_f32(cooldown), TimerMode::Once);
-        timer.pause();
-
-        Weapon {
-            name: String::from("Space Blaster"),
-            cooldown_timer: timer,
-            mounting_point: if friendly {
-                Transform::from_xyz(PLAYER_WIDTH, -PLAYER_WING_TIPS, 0.0)
-            } else {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            },
-            gun_positions: vec![Transform::from_xyz(0.0, 0.0, 0.0)],
-            projectile: if friendly {
-                Projectile {
-                    friendly: true,
-                    speed: 15.0,
-                    damage: 12,
-                    projectile_type: ProjectileType::Blaster,
-                    pushback: 0.0,
-                }
-            } else {
-                Projectile {
-                    friendly: false,
-                    speed: 5.0,
-                    damage: 50,
-                    projectile_type: ProjectileType::Blaster,
-                    pushback: 0.0,
+        if let Some(key) = self
+            .player_loadout
+            .iter()
+            .find(|key| !self.weapons.contains_key(*key))
+        {
+            return Err(format!(
+                "The player loadout names `{key}`, which is not in the weapon catalog."
+            ));
+        }
+        for (key, definition) in &self.weapons {
+            for stats in [&definition.player, &definition.enemy] {
+                if !stats.cooldown.is_finite() || stats.cooldown < 0.0 {
+                    return Err(format!(
+                        "The cooldown of `{key}` has to be zero or more seconds."
+                    ));
                 }
-            },
+            }
         }
+        Ok(())
     }
+}
 
-    pub fn grim(friendly: bool) -> Self {
-        let mut cooldown = 2500.0 / 40.0 / ORIGINAL_TARGET_FPS;
-        if !friendly {
-            cooldown *= 2.0
-        }
+impl WeaponCatalog {
+    pub fn weapon(&self, key: &str, friendly: bool) -> Weapon {
+        let definition = self
+            .weapons
+            .get(key)
+            .unwrap_or_else(|| panic!("No weapon named `{key}` in the weapon catalog."));
+        let stats = if friendly {
+            &definition.player
+        } else {
+            &definition.enemy
+        };
 
-        let mut timer = Timer::new(Duration::from_secs_f32(cooldown), TimerMode::Once);
+        let mut timer = Timer::new(Duration::from_secs_f32(stats.cooldown), TimerMode::Once);
         timer.pause();
 
         Weapon {
-            name: String::from("Grim Reaper"),
+            key: key.to_string(),
+            name: definition.name.clone(),
+            auto_fire: definition.auto_fire,
             cooldown_timer: timer,
-            mounting_point: if friendly {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            } else {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            },
-            gun_positions: vec![Transform::from_xyz(0.0, 0.0, 0.0)],
-            projectile: if friendly {
-                Projectile {
-                    friendly: true,
-                    speed: 5.0,
-                    damage: 50,
-                    projectile_type: ProjectileType::Grim,
-                    pushback: 0.0,
-                }
-            } else {
-                Projectile {
-                    friendly: false,
-                    speed: 4.0,
-                    damage: 40,
-                    projectile_type: ProjectileType::Grim,
-                    pushback: 0.0,
-                }
+            mounting_point: Transform::from_xyz(
+                stats.mounting_point.0,
+                stats.mounting_point.1,
+                0.0,
+            ),
+            gun_positions: stats
+                .gun_positions
+                .iter()
+                .map(|(x, y)| Transform::from_xyz(*x, *y, 0.0))
+                .collect(),
+            projectile: Projectile {
+                friendly,
+                speed: stats.projectile_speed,
+                damage: stats.damage,
+                hit_effect: definition.hit_effect,
+                pushback: stats.pushback,
             },
+            projectile_image: definition.projectile_image.clone(),
         }
     }
 
-    pub fn hammer(friendly: bool) -> Self {
-        let mut cooldown = 2000.0 / 40.0 / ORIGINAL_TARGET_FPS;
-        if !friendly {
-            cooldown *= 2.0
-        }
-
-        let mut timer = Timer::new(Duration::from_secs_f32(cooldown), TimerMode::Once);
-        timer.pause();
+    pub fn player_weapons(&self) -> Vec<Weapon> {
+        self.player_loadout
+            .iter()
+            .map(|key| self.weapon(key, true))
+            .collect()
+    }
 
-        Weapon {
-            name: String::from("Space Hammer"),
-            cooldown_timer: timer,
-            mounting_point: if friendly {
-                Transform::from_xyz(0.0, 6.5, 0.0)
-            } else {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            },
-            gun_positions: vec![
-                Transform::from_xyz(-24.0, 0.0, 0.0),
-                Transform::from_xyz(0.0, 0.0, 0.0),
-                Transform::from_xyz(24.0, 0.0, 0.0),
-            ],
-            projectile: if friendly {
-                Projectile {
-                    friendly: true,
-                    speed: 10.0,
-                    damage: 4,
-                    projectile_type: ProjectileType::Hammer,
-                    pushback: 36.0,
-                }
-            } else {
-                Projectile {
-                    friendly: false,
-                    speed: 7.0,
-                    damage: 4,
-                    projectile_type: ProjectileType::Hammer,
-                    pushback: 36.0,
-                }
-            },
-        }
+    pub fn projectile_images(&self) -> impl Iterator<Item = &Handle<Image>> {
+        self.weapons
+            .values()
+            .map(|definition| &definition.projectile_image)
     }
+}
 
-    pub fn ratata(friendly: bool) -> Self {
-        let mut cooldown = 4.0 / ORIGINAL_TARGET_FPS;
-        if !friendly {
-            cooldown *= 2.0
-        }
+#[derive(Deserialize)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub projectile_sprite: String,
+    #[serde(skip)]
+    pub projectile_image: Handle<Image>,
+    pub hit_effect: HitEffect,
+    /// Keeps firing while the player holds fire.
+    #[serde(default)]
+    pub auto_fire: bool,
+    /// Stats when mounted on the player.
+    pub player: WeaponStats,
+    /// Stats when mounted on an enemy.
+    pub enemy: WeaponStats,
+}
 
-        let mut timer = Timer::new(Duration::from_secs_f32(cooldown), TimerMode::Once);
-        timer.pause();
+#[derive(Deserialize)]
+pub struct WeaponStats {
+    /// Seconds between two shots.
+    pub cooldown: f32,
+    pub mounting_point: (f32, f32),
+    pub gun_positions: Vec<(f32, f32)>,
+    pub projectile_speed: f32,
+    pub damage: u32,
+    #[serde(default)]
+    pub pushback: f32,
+}
 
-        Weapon {
-            name: String::from("Ratata 9000"),
-            cooldown_timer: timer,
-            mounting_point: if friendly {
-                Transform::from_xyz(0.0, -PLAYER_WING_TIPS, 0.0)
-            } else {
-                Transform::from_xyz(0.0, 0.0, 0.0)
-            },
-            gun_positions: if friendly {
-                vec![
-                    Transform::from_xyz(-PLAYER_WIDTH, 0.0, 0.0),
-                    Transform::from_xyz(-PLAYER_WIDTH+10.0, 0.0, 0.0),
-                    Transform::from_xyz(PLAYER_WIDTH-10.0, 0.0, 0.0),
-                    Transform::from_xyz(PLAYER_WIDTH, 0.0, 0.0),
-                ]
-            } else {
-                vec![
-                    Transform::from_xyz(-6.0, 0.0, 0.0),
-                    Transform::from_xyz(6.0, 0.0, 0.0),
-                ]
-            },
-            projectile: if friendly {
-                Projectile {
-                    friendly: true,
-                    speed: 15.0,
-                    damage: 3,
-                    projectile_type: ProjectileType::Ratata,
-                    pushback: 0.0,
-                }
-            } else {
-                Projectile {
-                    friendly: false,
-                    speed: 12.0,
-                    damage: 3,
-                    projectile_type: ProjectileType::Ratata,
-                    pushback: 0.0,
+#[derive(Component, Clone)]
+pub struct Weapon {
+    /// Key of the weapon in the `WeaponCatalog`.
+    pub key: String,
+    pub name: String,
+    pub auto_fire: bool,
+    pub cooldown_timer: Timer,
+    pub gun_positions: Vec<Transform>,
+    pub mounting_point: Transform,
+    pub projectile: Projectile,
+    pub projectile_image: Handle<Image>,
+}
+
+/// Applies changes to the weapon catalog to all armed ships while the game is running.
+/// Only happens in dev builds, where assets are watched for changes.
+fn reload_weapons(
+    mut ev_asset: EventReader<AssetEvent<WeaponCatalog>>,
+    catalogs: Res<Assets<WeaponCatalog>>,
+    mut player_query: Query<&mut Player>,
+    mut enemies_query: Query<&mut Enemy>,
+) {
+    for ev in ev_asset.iter() {
+        if let AssetEvent::Modified { handle } = ev {
+            let catalog = catalogs.get(handle).unwrap();
+
+            for mut player in &mut player_query {
+                player.weapons = catalog.player_weapons();
+                player.current_weapon_index =
+                    player.current_weapon_index.min(player.weapons.len() - 1);
+            }
+
+            for mut enemy in &mut enemies_query {
+                if let Some(weapon) = &mut enemy.weapon {
+                    *weapon = catalog.weapon(&weapon.key, false);
                 }
-            },
+            }
         }
     }
 }
 
 fn player_shoot(
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player_position: Query<(&mut Player, &mut Transform, &Handle<Image>)>,
+    tick_input: Res<TickInput>,
+    settings: Res<Settings>,
+    mut player_position: Query<
+        (Entity, &mut Player, &mut Transform, &Collider),
+        Without<Respawning>,
+    >,
     assets: Res<Assets<Image>>,
-    my_assets: Res<MyAssets>,
+    masks: Res<CollisionMasks>,
 ) {
-    let (mut player, transform, player_img_handle) = player_position.single_mut();
-    let player_size = assets.get(player_img_handle).unwrap().size();
+    for (player_entity, mut player, transform, player_collider) in &mut player_position {
+        let actions = tick_input.player(player.index);
+        let weapon = player.current_weapon();
+        weapon.cooldown_timer.tick(fixed_time.period);
+
+        let hold_to_fire = weapon.auto_fire && settings.hold_to_fire();
+        let fire =
+            actions.just_pressed(Action::Fire) || (hold_to_fire && actions.pressed(Action::Fire));
+
+        if fire && (weapon.cooldown_timer.finished() || weapon.cooldown_timer.paused()) {
+            if weapon.cooldown_timer.paused() {
+                weapon.cooldown_timer.unpause();
+            }
 
-    let weapon = player.current_weapon();
-    weapon.cooldown_timer.tick(time.delta());
+            let projectile_collider =
+                Collider::from_image(&assets, &masks, &weapon.projectile_image);
 
-    if keyboard_input.just_pressed(KeyCode::J)
-        && (weapon.cooldown_timer.finished() || weapon.cooldown_timer.paused())
-    {
-        if weapon.cooldown_timer.paused() {
-            weapon.cooldown_timer.unpause();
-        }
+            for pos in &weapon.gun_positions {
+                let texture = weapon.projectile_image.clone();
 
-        for pos in &weapon.gun_positions {
-            let texture = weapon.projectile.image(&my_assets);
-            let projectile_size = assets.get(&texture).unwrap().size();
+                let translation = Vec3::new(
+                    transform.translation.x
+                        + weapon.mounting_point.translation.x
+                        + pos.translation.x,
+                    transform.translation.y
+                        + weapon.mounting_point.translation.y
+                        + pos.translation.y
+                        + player_collider.size.y / 2.0
+                        + projectile_collider.size.y / 2.0,
+                    Layers::Projectiles.order_nr(),
+                );
 
-            commands.spawn((
-                SpriteBundle {
-                    texture: texture,
-                    transform: Transform::from_xyz(
-                        transform.translation.x
-                            + weapon.mounting_point.translation.x
-                            + pos.translation.x,
-                        transform.translation.y
-                            + weapon.mounting_point.translation.y
-                            + pos.translation.y
-                            + player_size.y / 2.0
-                            + projectile_size.y / 2.0,
-                        Layers::Projectiles.order_nr(),
-                    ),
-                    ..default()
-                },
-                weapon.projectile,
-            ));
+                commands.spawn((
+                    SpriteBundle {
+                        texture: texture,
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                    weapon.projectile,
+                    projectile_collider.clone(),
+                    FiredBy(player_entity),
+                    Interpolated::new(translation),
+                ));
+            }
+            weapon.cooldown_timer.reset()
         }
-        weapon.cooldown_timer.reset()
     }
 }
 
 fn enemy_shoot(
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut commands: Commands,
-    mut enemies_query: Query<
-        (Entity, &mut Enemy, &mut Transform, &mut Handle<Image>),
-        Without<Player>,
-    >,
-    player_query: Query<(&mut Player, &mut Transform, &Handle<Image>), Without<Enemy>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut enemies_query: Query<(Entity, &mut Enemy, &mut Transform, &Collider), Without<Player>>,
+    player_query: Query<(&Player, &Transform, &Collider), Without<Respawning>>,
     assets: Res<Assets<Image>>,
-    my_assets: Res<MyAssets>,
+    masks: Res<CollisionMasks>,
 ) {
-    let window = window_query.single();
-
-    for (_enemy_entity, mut enemy, transform, img) in &mut enemies_query {
-        let enemy_size = assets.get(&img).unwrap().size();
-
+    for (_enemy_entity, mut enemy, transform, enemy_collider) in &mut enemies_query {
         if let Some(weapon) = &mut enemy.weapon {
-            weapon.cooldown_timer.tick(time.delta());
-
-            let (_player, player_pos, player_img) = player_query.single();
-            let player_size = assets.get(&player_img).unwrap().size();
+            weapon.cooldown_timer.tick(fixed_time.period);
 
-            let aim_height = window.height() / 2.0 + transform.translation.y;
+            let aim_height = PLAYFIELD.y / 2.0 + transform.translation.y;
             let aim_pos = Vec3 {
                 x: transform.translation.x,
                 y: transform.translation.y - aim_height / 2.0,
                 z: Layers::Actors.order_nr(),
             };
 
-            let aim_size = Vec2 {
-                x: player_size.x,
-                y: aim_height,
-            };
+            // Enemies only aim at the nearest player.
+            let target = player_query.iter().min_by(|(_, a, _), (_, b, _)| {
+                let distance = |player: &Transform| {
+                    player
+                        .translation
+                        .truncate()
+                        .distance_squared(transform.translation.truncate())
+                };
+                distance(a).total_cmp(&distance(b))
+            });
+            let in_sight = target.is_some_and(|(_player, player_pos, player_collider)| {
+                let aim = Collider::new(Vec2 {
+                    x: player_collider.hitbox.x,
+                    y: aim_height,
+                });
+                player_collider.collides(player_pos.translation, &aim, aim_pos)
+            });
 
             // Includes some buffer to give the player a slight advantage.
             let fully_visible =
-                transform.translation.y + enemy_size.y / 2.0 <= window.height() / 2.0 - 12.0;
+                transform.translation.y + enemy_collider.size.y / 2.0 <= PLAYFIELD.y / 2.0 - 12.0;
 
             if (weapon.cooldown_timer.finished() || weapon.cooldown_timer.paused())
                 && fully_visible
-                && collide(player_pos.translation, player_size, aim_pos, aim_size).is_some()
+                && in_sight
             {
                 if weapon.cooldown_timer.paused() {
                     weapon.cooldown_timer.unpause();
                 }
 
+                let projectile_collider =
+                    Collider::from_flipped_image(&assets, &masks, &weapon.projectile_image);
+
                 for pos in &weapon.gun_positions {
-                    let texture = weapon.projectile.image(&my_assets);
-                    let projectile_size = assets.get(&texture).unwrap().size();
+                    let texture = weapon.projectile_image.clone();
+
+                    let translation = Vec3::new(
+                        transform.translation.x
+                            + weapon.mounting_point.translation.x
+                            + pos.translation.x,
+                        transform.translation.y
+                            + weapon.mounting_point.translation.y
+                            + pos.translation.y
+                            - enemy_collider.size.y / 2.0
+                            - projectile_collider.size.y / 2.0,
+                        Layers::Projectiles.order_nr(),
+                    );
 
                     commands.spawn((
                         SpriteBundle {
                             texture: texture,
-                            transform: Transform::from_xyz(
-                                transform.translation.x
-                                    + weapon.mounting_point.translation.x
-                                    + pos.translation.x,
-                                transform.translation.y
-                                    + weapon.mounting_point.translation.y
-                                    + pos.translation.y
-                                    - enemy_size.y / 2.0
-                                    - projectile_size.y / 2.0,
-                                Layers::Projectiles.order_nr(),
-                            ),
+                            transform: Transform::from_translation(translation),
                             sprite: Sprite {
                                 flip_y: true,
                                 ..default()
@@ -393,6 +382,8 @@ fn enemy_shoot(
                             ..default()
                         },
                         weapon.projectile,
+                        projectile_collider.clone(),
+                        Interpolated::new(translation),
                     ));
                 }
                 weapon.cooldown_timer.reset()
@@ -403,26 +394,17 @@ fn enemy_shoot(
 
 fn projectile_move(
     mut commands: Commands,
-    time: Res<Time>,
-    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform, &Handle<Image>)>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    assets: Res<Assets<Image>>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform, &Collider)>,
 ) {
-    let window = window_query.single();
-
-    for (proj_entity, projectile, mut transform, img_handle) in &mut projectiles {
+    for (proj_entity, projectile, mut transform, collider) in &mut projectiles {
         if projectile.friendly {
-            transform.translation.y = transform.translation.y
-                + projectile.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
+            transform.translation.y = transform.translation.y + projectile.speed;
         } else {
-            transform.translation.y = transform.translation.y
-                - projectile.speed * time.delta_seconds() * ORIGINAL_TARGET_FPS;
+            transform.translation.y = transform.translation.y - projectile.speed;
         }
 
-        let proj_size = assets.get(img_handle).unwrap().size();
-
-        if projectile_past_top(transform.translation.y, window, proj_size)
-            || projectile_past_bottom(transform.translation.y, window, proj_size)
+        if projectile_past_top(transform.translation.y, collider.size)
+            || projectile_past_bottom(transform.translation.y, collider.size)
         {
             commands.entity(proj_entity).despawn();
         }
@@ -430,66 +412,85 @@ fn projectile_move(
 }
 
 // TODO: DRYup helpers like that.
-fn projectile_past_top(y: f32, window: &Window, proj_size: Vec2) -> bool {
-    let max_y = (window.height() / 2.) + (proj_size.y / 2.);
+fn projectile_past_top(y: f32, proj_size: Vec2) -> bool {
+    let max_y = (PLAYFIELD.y / 2.) + (proj_size.y / 2.);
     y > max_y
 }
 
-fn projectile_past_bottom(y: f32, window: &Window, proj_size: Vec2) -> bool {
-    let min_y = -(window.height() / 2.) - (proj_size.y / 2.);
+fn projectile_past_bottom(y: f32, proj_size: Vec2) -> bool {
+    let min_y = -(PLAYFIELD.y / 2.) - (proj_size.y / 2.);
     y < min_y
 }
 
-fn projectile_collision(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn projectile_collision(
     mut commands: Commands,
-    mut game: ResMut<Game>,
-    assets: Res<Assets<Image>>,
     mut player_query: Query<
-        (Entity, &mut Player, &mut Transform, &mut Handle<Image>),
+        (Entity, &mut Player, &mut Transform, &Collider),
         (Without<Enemy>, Without<Projectile>),
     >,
+    vulnerable_query: Query<(), (With<Player>, Vulnerable)>,
     mut enemies_query: Query<
-        (Entity, &mut Enemy, &mut Transform, &mut Handle<Image>),
+        (
+            Entity,
+            &mut Enemy,
+            &mut Transform,
+            &Collider,
+            Option<&HitZones>,
+        ),
         (Without<Player>, Without<Projectile>),
     >,
     mut projectiles: Query<
-        (Entity, &mut Projectile, &mut Transform, &Handle<Image>),
+        (
+            Entity,
+            &mut Projectile,
+            &mut Transform,
+            &Collider,
+            Option<&FiredBy>,
+        ),
         (Without<Enemy>, Without<Player>),
     >,
+    mut grid: ResMut<CollisionGrid>,
     my_assets: Res<MyAssets>,
+    mut ev_enemydestroyed: EventWriter<EnemyDestroyedEvent>,
 ) {
-    for (proj_entity, projectile, transform, img_handle) in &mut projectiles {
-        let projectile_size = assets.get(img_handle).unwrap().size();
-
+    for (proj_entity, projectile, transform, projectile_collider, fired_by) in &mut projectiles {
         if projectile.friendly {
-            for (enemy_entity, mut enemy, mut pos, img) in &mut enemies_query {
-                let enemy_size = assets.get(&img).unwrap().size();
-
-                if collide(
+            for candidate in grid.candidates(transform.translation, projectile_collider) {
+                let Ok((enemy_entity, mut enemy, mut pos, enemy_collider, hit_zones)) =
+                    enemies_query.get_mut(candidate)
+                else {
+                    continue;
+                };
+                // Already shot down this tick, only its despawn is left.
+                if enemy.health == 0 {
+                    continue;
+                }
+                if enemy_collider.collides(
                     pos.translation,
-                    enemy_size,
+                    projectile_collider,
                     transform.translation,
-                    projectile_size,
-                )
-                .is_some()
-                {
+                ) {
                     commands.entity(proj_entity).despawn();
 
+                    let damage = match hit_zones {
+                        Some(zones) => zones.damage(
+                            projectile.damage,
+                            (transform.translation - pos.translation).truncate(),
+                        ),
+                        None => projectile.damage,
+                    };
+
                     pos.translation.y += projectile.pushback;
+                    grid.insert(enemy_entity, pos.translation, enemy_collider);
 
-                    enemy.health = if let Some(i) = enemy.health.checked_sub(projectile.damage) {
+                    enemy.health = if let Some(i) = enemy.health.checked_sub(damage) {
                         i
                     } else {
                         0
                     };
 
-                    let hit_texture = match projectile.projectile_type {
-                        ProjectileType::Blaster => my_assets.hit_red.clone(),
-                        ProjectileType::Grim => my_assets.hit_blue.clone(),
-                        ProjectileType::Hammer => my_assets.hit_red.clone(),
-                        ProjectileType::Ratata => my_assets.hit_blue.clone(),
-                        ProjectileType::Stomp => my_assets.hit_green.clone(),
-                    };
+                    let hit_texture = projectile.hit_effect.texture_atlas(&my_assets);
 
                     let animation_indices = AnimationIndices { first: 0, last: 1 };
                     commands.spawn((
@@ -508,8 +509,16 @@ fn projectile_collision(
                     ));
 
                     if enemy.health == 0 {
-                        game.score += enemy.bounty;
+                        if let Some(Ok((_, mut player, _, _))) =
+                            fired_by.map(|fired_by| player_query.get_mut(fired_by.0))
+                        {
+                            player.score += enemy.bounty;
+                        }
                         commands.entity(enemy_entity).despawn();
+                        ev_enemydestroyed.send(EnemyDestroyedEvent {
+                            enemy: enemy_entity,
+                            player: fired_by.map(|fired_by| fired_by.0),
+                        });
 
                         let animation_indices = AnimationIndices { first: 0, last: 11 };
                         commands.spawn((
@@ -527,37 +536,32 @@ fn projectile_collision(
                             AnimationTimer(Timer::from_seconds(0.019, TimerMode::Repeating)),
                         ));
                     }
+                    break;
                 }
             }
         } else {
-            let (_player_entity, _player, mut pos, img) = player_query.single_mut();
-            let player_size = assets.get(&img).unwrap().size();
-
-            if collide(
-                pos.translation,
-                player_size,
-                transform.translation,
-                projectile_size,
-            )
-            .is_some()
-            {
+            for (player_entity, mut player, mut pos, player_collider) in &mut player_query {
+                if !vulnerable_query.contains(player_entity)
+                    || !player_collider.collides(
+                        pos.translation,
+                        projectile_collider,
+                        transform.translation,
+                    )
+                {
+                    continue;
+                }
+
                 commands.entity(proj_entity).despawn();
 
                 pos.translation.y -= projectile.pushback;
 
-                game.health = if let Some(i) = game.health.checked_sub(projectile.damage) {
+                player.health = if let Some(i) = player.health.checked_sub(projectile.damage) {
                     i
                 } else {
                     0
                 };
 
-                let hit_texture = match projectile.projectile_type {
-                    ProjectileType::Blaster => my_assets.hit_red.clone(),
-                    ProjectileType::Grim => my_assets.hit_blue.clone(),
-                    ProjectileType::Hammer => my_assets.hit_red.clone(),
-                    ProjectileType::Ratata => my_assets.hit_blue.clone(),
-                    ProjectileType::Stomp => my_assets.hit_green.clone(),
-                };
+                let hit_texture = projectile.hit_effect.texture_atlas(&my_assets);
 
                 let animation_indices = AnimationIndices { first: 0, last: 1 };
                 commands.spawn((
@@ -574,53 +578,236 @@ fn projectile_collision(
                     animation_indices,
                     AnimationTimer(Timer::from_seconds(0.03, TimerMode::Repeating)),
                 ));
+                // A projectile only hits one player.
+                break;
             }
         }
     }
 }
 
-pub struct WeaponSwitchedEvent;
+/// Sent with the player who switched.
+pub struct WeaponSwitchedEvent(pub Entity);
 
 fn weapon_switching(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player_query: Query<(&mut Player, &mut Transform, &Handle<Image>)>,
+    tick_input: Res<TickInput>,
+    mut player_query: Query<(Entity, &mut Player)>,
     mut ev_weaponswitched: EventWriter<WeaponSwitchedEvent>,
-    game: Res<Game>,
 ) {
-    let (mut player, _transform, _player_img_handle) = player_query.single_mut();
+    for (entity, mut player) in &mut player_query {
+        // One more weapon gets unlocked with every level.
+        let unlocked = (player.level as usize).min(player.weapons.len());
+        let current = player.current_weapon_index;
+        let actions = tick_input.player(player.index);
+
+        let mut next = Action::WEAPONS[..unlocked]
+            .iter()
+            .rposition(|action| actions.just_pressed(*action))
+            .unwrap_or(current);
+        if actions.just_pressed(Action::NextWeapon) {
+            next = (current + 1) % unlocked;
+        }
+        if actions.just_pressed(Action::PreviousWeapon) {
+            next = (current + unlocked - 1) % unlocked;
+        }
 
-    if keyboard_input.just_pressed(KeyCode::Key1) {
-        if player.current_weapon_index != 0 {
-            player.current_weapon_index = 0;
-            ev_weaponswitched.send(WeaponSwitchedEvent);
+        if next != current {
+            player.current_weapon_index = next;
+            ev_weaponswitched.send(WeaponSwitchedEvent(entity));
         }
     }
+}
 
-    if keyboard_input.just_pressed(KeyCode::Key2) && game.level >= 2 {
-        if player.current_weapon_index != 1 {
-            player.current_weapon_index = 1;
-            ev_weaponswitched.send(WeaponSwitchedEvent);
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{FiredBy, HitEffect, Projectile, WeaponCatalog};
+    use crate::{
+        coop::GameMode, ron_asset::RonAsset, settings::Settings, test_harness::TestGame, Enemy,
+        Player, PLAYER_HEALTH,
+    };
+
+    fn projectile(friendly: bool, damage: u32) -> Projectile {
+        Projectile {
+            speed: 0.0,
+            damage,
+            friendly,
+            hit_effect: HitEffect::Red,
+            pushback: 0.0,
         }
     }
 
-    if keyboard_input.just_pressed(KeyCode::Key3) && game.level >= 3 {
-        if player.current_weapon_index != 2 {
-            player.current_weapon_index = 2;
-            ev_weaponswitched.send(WeaponSwitchedEvent);
-        }
+    #[test]
+    fn friendly_projectile_damages_enemy() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("big_ship", Vec2::new(0.0, 200.0));
+        let projectile = game.spawn_projectile(projectile(true, 6), Vec2::new(0.0, 200.0));
+
+        game.step();
+
+        assert!(!game.exists(projectile));
+        assert_eq!(game.app.world.get::<Enemy>(enemy).unwrap().health, 94);
+        assert_eq!(game.player_state().score, 0);
     }
 
-    if keyboard_input.just_pressed(KeyCode::Key4) && game.level >= 4 {
-        if player.current_weapon_index != 3 {
-            player.current_weapon_index = 3;
-            ev_weaponswitched.send(WeaponSwitchedEvent);
-        }
+    #[test]
+    fn destroying_enemy_grants_bounty() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("trespasser", Vec2::new(100.0, 200.0));
+        game.spawn_projectile(projectile(true, 50), Vec2::new(100.0, 200.0));
+
+        game.step();
+
+        assert!(!game.exists(enemy));
+        assert_eq!(game.player_state().score, 35);
     }
 
-    if keyboard_input.just_pressed(KeyCode::Key5) && game.level >= 5 {
-        if player.current_weapon_index != 4 {
-            player.current_weapon_index = 4;
-            ev_weaponswitched.send(WeaponSwitchedEvent);
-        }
+    #[test]
+    fn ships_are_only_destroyed_once() {
+        let mut game = TestGame::new();
+        let enemy = game.spawn_enemy("trespasser", Vec2::new(100.0, 200.0));
+        game.spawn_projectile(projectile(true, 50), Vec2::new(100.0, 200.0));
+        game.spawn_projectile(projectile(true, 50), Vec2::new(100.0, 200.0));
+
+        game.step();
+
+        assert!(!game.exists(enemy));
+        assert_eq!(game.player_state().score, 35);
+        assert_eq!(game.projectile_count(), 1);
+    }
+
+    #[test]
+    fn projectiles_only_hit_one_ship() {
+        let mut game = TestGame::new();
+        let first = game.spawn_enemy("big_ship", Vec2::new(0.0, 200.0));
+        let second = game.spawn_enemy("big_ship", Vec2::new(0.0, 200.0));
+        game.spawn_projectile(projectile(true, 6), Vec2::new(0.0, 200.0));
+
+        game.step();
+
+        let health = |enemy| game.app.world.get::<Enemy>(enemy).unwrap().health;
+        assert_eq!(health(first) + health(second), 194);
+    }
+
+    #[test]
+    fn bounty_goes_to_the_player_who_fired() {
+        let mut game = TestGame::with_mode(GameMode::Coop);
+        let [first, second] = game.players()[..] else {
+            panic!("Co-op needs two players");
+        };
+        game.spawn_enemy("trespasser", Vec2::new(100.0, 200.0));
+        let projectile = game.spawn_projectile(projectile(true, 50), Vec2::new(100.0, 200.0));
+        game.app
+            .world
+            .entity_mut(projectile)
+            .insert(FiredBy(second));
+
+        game.step();
+
+        assert_eq!(game.app.world.get::<Player>(first).unwrap().score, 0);
+        assert_eq!(game.app.world.get::<Player>(second).unwrap().score, 35);
+    }
+
+    #[test]
+    fn enemy_projectile_damages_player() {
+        let mut game = TestGame::new();
+        let projectile = game.spawn_projectile(projectile(false, 40), Vec2::ZERO);
+
+        game.step();
+
+        assert!(!game.exists(projectile));
+        assert_eq!(game.player_state().health, PLAYER_HEALTH - 40);
+    }
+
+    #[test]
+    fn friendly_projectile_ignores_player() {
+        let mut game = TestGame::new();
+        let projectile = game.spawn_projectile(projectile(true, 40), Vec2::ZERO);
+
+        game.step();
+
+        assert!(game.exists(projectile));
+        assert_eq!(game.player_state().health, PLAYER_HEALTH);
+    }
+
+    #[test]
+    fn weapons_can_be_cycled_through_once_unlocked() {
+        let mut game = TestGame::new();
+        game.player_state_mut().level = 2;
+        let player = game.player();
+        let weapon_index = |game: &TestGame| {
+            game.app
+                .world
+                .get::<Player>(player)
+                .unwrap()
+                .current_weapon_index
+        };
+
+        game.tap(KeyCode::E);
+        assert_eq!(weapon_index(&game), 1);
+
+        game.tap(KeyCode::E);
+        assert_eq!(weapon_index(&game), 0);
+
+        game.tap(KeyCode::Key3);
+        assert_eq!(weapon_index(&game), 0);
+    }
+
+    /// Holds fire for half a second with the weapon in `slot`, returns the projectiles fired.
+    fn hold_fire(slot: usize, hold_to_fire: bool) -> usize {
+        let mut game = TestGame::new();
+        game.app.insert_resource(Settings {
+            hold_to_fire,
+            ..default()
+        });
+        let player = game.player();
+        game.app
+            .world
+            .get_mut::<Player>(player)
+            .unwrap()
+            .current_weapon_index = slot;
+
+        game.press(KeyCode::J);
+        game.step_frames(20);
+
+        game.projectile_count()
+    }
+
+    #[test]
+    fn auto_fire_weapons_keep_firing_while_held() {
+        // Ratata 9000 fires four projectiles per shot.
+        assert!(hold_fire(4, true) > 4);
+        assert_eq!(hold_fire(4, false), 4);
+    }
+
+    #[test]
+    fn other_weapons_need_a_press_per_shot() {
+        assert_eq!(hold_fire(2, true), 1);
+    }
+
+    #[test]
+    fn weapon_catalogs_that_would_crash_the_game_are_rejected() {
+        let arsenal = || -> WeaponCatalog {
+            ron::from_str(include_str!("../assets/data/arsenal.weapons.ron")).unwrap()
+        };
+        assert!(arsenal().validate().is_ok());
+
+        let mut catalog = arsenal();
+        catalog.player_loadout.clear();
+        assert!(catalog.validate().is_err());
+
+        let mut catalog = arsenal();
+        catalog.player_loadout.push("no_such_weapon".to_string());
+        assert!(catalog.validate().is_err());
+
+        let mut catalog = arsenal();
+        let definition = catalog.weapons.values_mut().next().unwrap();
+        definition.enemy.cooldown = f32::NAN;
+        assert!(catalog.validate().is_err());
+
+        let mut catalog = arsenal();
+        let definition = catalog.weapons.values_mut().next().unwrap();
+        definition.player.cooldown = -1.0;
+        assert!(catalog.validate().is_err());
     }
 }
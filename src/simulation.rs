@@ -0,0 +1,200 @@
+//! Gameplay runs in `CoreSchedule::FixedUpdate` at the 40 FPS of the original game,
+//! so it behaves the same on every machine. Sprites are interpolated between ticks for rendering.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    controls::{ActionState, ActionSystem, SecondPlayerActions},
+    AppState,
+};
+
+/// Ticks per second, all speeds are in pixels per tick.
+pub const TICK_RATE: u64 = 40;
+pub const TICK: Duration = Duration::from_millis(1000 / TICK_RATE);
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FixedTime::new(TICK))
+            .init_resource::<TickInput>()
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_set(
+                    TickSet::Gameplay
+                        .run_if(in_state(AppState::InGame))
+                        .run_if(state_unchanged)
+                        .before(TickSet::Interpolation),
+                );
+            })
+            .add_system(
+                collect_tick_input
+                    .in_base_set(CoreSet::PreUpdate)
+                    .in_set(TickInputSystem)
+                    .after(ActionSystem),
+            )
+            .add_system(restore_simulated_translation.in_base_set(CoreSet::First))
+            .add_systems(
+                (track_translation, clear_tick_input)
+                    .in_set(TickSet::Interpolation)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                interpolate_translation
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TickSet {
+    /// Only runs while the game is played.
+    Gameplay,
+    /// Runs last in every tick.
+    Interpolation,
+}
+
+/// A frame can run several ticks, but once one of them leaves the game (like on game over)
+/// the rest of them must not keep playing until the state actually changes.
+fn state_unchanged(next_state: Res<NextState<AppState>>) -> bool {
+    next_state.0.is_none()
+}
+
+/// Collects `ActionState` and `SecondPlayerActions` into `TickInput`, systems changing the actions have to run before.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TickInputSystem;
+
+/// The actions as seen by the ticks. Actions triggered in frames without a tick are kept
+/// for the next one, so that no shot or weapon switch gets lost.
+#[derive(Resource, Default)]
+pub struct TickInput([ActionState; 2]);
+
+impl TickInput {
+    /// Actions of the player with `index`, see `Player::index`.
+    pub fn player(&self, index: usize) -> &ActionState {
+        &self.0[index]
+    }
+
+    /// For inputs coming from elsewhere, like the other player in netplay.
+    pub fn player_mut(&mut self, index: usize) -> &mut ActionState {
+        &mut self.0[index]
+    }
+}
+
+fn collect_tick_input(
+    actions: Res<ActionState>,
+    second_actions: Res<SecondPlayerActions>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    for (tick_actions, actions) in tick_input.0.iter_mut().zip([&*actions, &second_actions.0]) {
+        tick_actions.movement = actions.movement;
+        tick_actions.pressed = actions.pressed;
+        tick_actions.just_pressed |= actions.just_pressed;
+    }
+}
+
+fn clear_tick_input(mut tick_input: ResMut<TickInput>) {
+    for tick_actions in &mut tick_input.0 {
+        tick_actions.just_pressed = 0;
+    }
+}
+
+/// Where an entity is at the last two ticks, `Transform` only shows it in between.
+/// Needs to be reset with `new` when teleporting an entity outside of the ticks.
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl Interpolated {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    /// The translation as of the last tick.
+    pub fn current(&self) -> Vec3 {
+        self.current
+    }
+}
+
+/// Ticks have to continue from where the last one left off, not from what was rendered.
+fn restore_simulated_translation(mut query: Query<(&Interpolated, &mut Transform)>) {
+    for (interpolated, mut transform) in &mut query {
+        transform.translation = interpolated.current;
+    }
+}
+
+fn track_translation(mut query: Query<(&mut Interpolated, &Transform)>) {
+    for (mut interpolated, transform) in &mut query {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+    }
+}
+
+fn interpolate_translation(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&Interpolated, &mut Transform)>,
+) {
+    let alpha = fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32();
+
+    for (interpolated, mut transform) in &mut query {
+        transform.translation = interpolated
+            .previous
+            .lerp(interpolated.current, alpha.min(1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::TICK;
+    use crate::{test_harness::TestGame, AppState};
+
+    #[test]
+    fn movement_does_not_depend_on_the_frame_rate() {
+        let mut game = TestGame::new();
+        let player = game.player();
+
+        game.press(KeyCode::D);
+        for _ in 0..20 {
+            game.step_by(TICK / 2);
+        }
+
+        assert!((game.position(player).x - 56.18).abs() < 0.01);
+    }
+
+    #[test]
+    fn sprites_are_rendered_between_ticks() {
+        let mut game = TestGame::new();
+        let player = game.player();
+
+        game.press(KeyCode::D);
+        game.step();
+        game.step_by(TICK / 2);
+
+        let transform = game.app.world.get::<Transform>(player).unwrap();
+        assert!((game.position(player).x - 5.618).abs() < 0.001);
+        assert!((transform.translation.x - 2.809).abs() < 0.001);
+    }
+
+    #[test]
+    fn no_more_ticks_are_played_after_game_over() {
+        let mut game = TestGame::new();
+        let player = game.player();
+
+        game.press(KeyCode::D);
+        game.game_mut().earth_health = 0;
+        game.step_by(TICK * 3);
+        game.step();
+
+        assert_eq!(game.state(), AppState::GameOver);
+        assert!((game.position(player).x - 5.618).abs() < 0.001);
+    }
+}
@@ -0,0 +1,36 @@
+//! Small files kept between runs, in the working directory (native)
+//! or the `localStorage` of the browser (wasm).
+
+use bevy::prelude::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    std::fs::read_to_string(format!("{name}.ron")).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("ssp.{name}")).ok()?
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, contents: &str) {
+    let path = format!("{name}.ron");
+    if let Err(err) = std::fs::write(&path, contents) {
+        error!("Could not save {path}: {err}");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(name: &str, contents: &str) {
+    let saved =
+        local_storage().and_then(|storage| storage.set_item(&format!("ssp.{name}"), contents).ok());
+    if saved.is_none() {
+        error!("Could not save {name} to the local storage");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
@@ -0,0 +1,343 @@
+//! Runs the game headless with stubbed sprites, so systems can be stepped frame by frame in tests.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    asset::AssetPlugin,
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, ButtonState, InputPlugin},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    time::TimeUpdateStrategy,
+};
+
+use crate::{
+    collision::{Collider, CollisionMasks},
+    coop::GameMode,
+    enemy_spawning::{EnemyArchetypes, LevelScript, Wave, WaveScript},
+    movement::Movement,
+    netplay::NetplayPlugin,
+    rng::GameRng,
+    shooting::{FiredBy, Projectile, WeaponCatalog},
+    simulation::{Interpolated, TICK},
+    AppState, Enemy, Game, GamePlugin, Layers, MyAssets, Player,
+};
+
+pub const PLAYER_SIZE: Vec2 = Vec2::new(99.0, 75.0);
+pub const SHIP_SIZE: Vec2 = Vec2::new(100.0, 80.0);
+pub const PROJECTILE_SIZE: Vec2 = Vec2::new(10.0, 20.0);
+
+pub struct TestGame {
+    pub app: App,
+    now: Instant,
+}
+
+impl TestGame {
+    /// Starts a game without any waves, enemies have to be spawned by the test.
+    pub fn new() -> Self {
+        Self::with_waves(no_waves())
+    }
+
+    pub fn with_waves(waves: WaveScript) -> Self {
+        Self::with_seed(waves, 0)
+    }
+
+    /// Starts a game without any waves for the players of `mode`.
+    pub fn with_mode(mode: GameMode) -> Self {
+        Self::start(no_waves(), 0, mode, NetplayPlugin::Off)
+    }
+
+    pub fn with_seed(waves: WaveScript, seed: u64) -> Self {
+        Self::start(waves, seed, GameMode::Solo, NetplayPlugin::Off)
+    }
+
+    /// Waits in the menu until the netplay connection starts the game.
+    pub fn with_netplay(netplay: NetplayPlugin) -> Self {
+        Self::start(no_waves(), 0, GameMode::Solo, netplay)
+    }
+
+    fn start(waves: WaveScript, seed: u64, mode: GameMode, netplay: NetplayPlugin) -> Self {
+        let state = match netplay {
+            NetplayPlugin::Off => AppState::InGame,
+            _ => AppState::MainMenu,
+        };
+
+        let mut app = App::new();
+        app.insert_resource(mode)
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>()
+            .insert_resource(GameRng::new(seed))
+            .add_state::<AppState>()
+            .add_plugin(GamePlugin)
+            .add_plugin(netplay);
+
+        let my_assets = stub_assets(&mut app.world, waves);
+        app.insert_resource(my_assets);
+        app.world.resource_mut::<NextState<AppState>>().set(state);
+
+        // The very first frame never has a delta, so it doesn't run a tick.
+        let now = app.world.resource::<Time>().startup();
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(now));
+        app.update();
+
+        let mut test_game = Self { app, now };
+        test_game.step();
+        test_game
+    }
+
+    /// Advances the game by exactly one frame, which runs exactly one tick.
+    pub fn step(&mut self) {
+        self.step_by(TICK);
+    }
+
+    pub fn step_by(&mut self, frame_time: Duration) {
+        self.now += frame_time;
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+        self.app.update();
+    }
+
+    pub fn step_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        self.app.world.resource::<Game>()
+    }
+
+    pub fn game_mut(&mut self) -> Mut<'_, Game> {
+        self.app.world.resource_mut::<Game>()
+    }
+
+    pub fn state(&self) -> AppState {
+        self.app.world.resource::<State<AppState>>().0.clone()
+    }
+
+    /// The first player.
+    pub fn player(&mut self) -> Entity {
+        self.players()[0]
+    }
+
+    /// Sorted by their index.
+    pub fn players(&mut self) -> Vec<Entity> {
+        let mut players: Vec<_> = self
+            .app
+            .world
+            .query::<(Entity, &Player)>()
+            .iter(&self.app.world)
+            .map(|(entity, player)| (player.index, entity))
+            .collect();
+        players.sort();
+        players.into_iter().map(|(_index, entity)| entity).collect()
+    }
+
+    pub fn player_state(&mut self) -> &Player {
+        let player = self.player();
+        self.app.world.get::<Player>(player).unwrap()
+    }
+
+    pub fn player_state_mut(&mut self) -> Mut<'_, Player> {
+        let player = self.player();
+        self.app.world.get_mut::<Player>(player).unwrap()
+    }
+
+    /// Presses a key and keeps it pressed, it's just pressed in the next frame.
+    pub fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+    }
+
+    /// Presses and releases a key within one frame, for things reacting to `just_pressed`.
+    pub fn tap(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+        self.step();
+        self.send_key(key, ButtonState::Released);
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app
+            .world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+    }
+
+    pub fn exists(&self, entity: Entity) -> bool {
+        self.app.world.get_entity(entity).is_some()
+    }
+
+    /// Where the entity is in the simulation, `Transform` is interpolated for rendering.
+    pub fn position(&self, entity: Entity) -> Vec3 {
+        self.app
+            .world
+            .get::<Interpolated>(entity)
+            .unwrap()
+            .current()
+    }
+
+    pub fn enemy_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<Enemy>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn projectile_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<Projectile>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn spawn_enemy(&mut self, archetype: &str, position: Vec2) -> Entity {
+        let (enemy, texture, collider, movement) =
+            self.app
+                .world
+                .resource_scope(|world, mut rng: Mut<GameRng>| {
+                    let my_assets = world.resource::<MyAssets>();
+                    let weapons = world.resource::<Assets<WeaponCatalog>>();
+                    let enemies = world.resource::<Assets<EnemyArchetypes>>();
+                    let images = world.resource::<Assets<Image>>();
+                    let masks = world.resource::<CollisionMasks>();
+
+                    let weapons = weapons.get(&my_assets.weapons).unwrap();
+                    let enemies = enemies.get(&my_assets.enemies).unwrap();
+                    (
+                        enemies.enemy(archetype, weapons, &mut rng.gameplay),
+                        enemies.get(archetype).image.clone(),
+                        enemies.collider(archetype, images, masks),
+                        enemies.get(archetype).movement.clone(),
+                    )
+                });
+
+        let translation = position.extend(Layers::Actors.order_nr());
+        self.app
+            .world
+            .spawn((
+                SpriteBundle {
+                    texture,
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                },
+                enemy,
+                Movement::new(movement),
+                collider,
+                Interpolated::new(translation),
+            ))
+            .id()
+    }
+
+    pub fn spawn_projectile(&mut self, projectile: Projectile, position: Vec2) -> Entity {
+        let texture = self
+            .app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(stub_image(PROJECTILE_SIZE));
+
+        let translation = position.extend(Layers::Projectiles.order_nr());
+        let player = self.player();
+        let mut entity = self.app.world.spawn((
+            SpriteBundle {
+                texture,
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            projectile,
+            Collider::new(PROJECTILE_SIZE),
+            Interpolated::new(translation),
+        ));
+        if projectile.friendly {
+            entity.insert(FiredBy(player));
+        }
+        entity.id()
+    }
+}
+
+fn no_waves() -> WaveScript {
+    WaveScript {
+        levels: vec![LevelScript {
+            waves: vec![Wave { entries: vec![] }],
+            boss: None,
+        }],
+    }
+}
+
+fn stub_image(size: Vec2) -> Image {
+    Image::new_fill(
+        Extent3d {
+            width: size.x as u32,
+            height: size.y as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Uses the real data files, but replaces every sprite by a blank image of a known size.
+fn stub_assets(world: &mut World, waves: WaveScript) -> MyAssets {
+    let mut images = world.resource_mut::<Assets<Image>>();
+    let player = images.add(stub_image(PLAYER_SIZE));
+    let ship = images.add(stub_image(SHIP_SIZE));
+    let projectile = images.add(stub_image(PROJECTILE_SIZE));
+    let backdrop = images.add(stub_image(Vec2::new(40.0, 40.0)));
+
+    let mut atlases = world.resource_mut::<Assets<TextureAtlas>>();
+    let atlas = atlases.add(TextureAtlas::from_grid(
+        backdrop.clone(),
+        Vec2::new(20.0, 20.0),
+        2,
+        1,
+        None,
+        None,
+    ));
+
+    let mut weapons: WeaponCatalog =
+        ron::from_str(include_str!("../assets/data/arsenal.weapons.ron")).unwrap();
+    for definition in weapons.weapons.values_mut() {
+        definition.projectile_image = projectile.clone();
+    }
+
+    let mut enemies: EnemyArchetypes =
+        ron::from_str(include_str!("../assets/data/fleet.enemies.ron")).unwrap();
+    for archetype in enemies.archetypes.values_mut() {
+        archetype.image = ship.clone();
+    }
+    for boss in enemies.bosses.values_mut() {
+        boss.image = ship.clone();
+    }
+
+    MyAssets {
+        player,
+        star: backdrop.clone(),
+        star1: backdrop.clone(),
+        star2: backdrop.clone(),
+        planet00: backdrop.clone(),
+        planet01: backdrop.clone(),
+        planet02: backdrop.clone(),
+        planet03: backdrop.clone(),
+        planet04: backdrop.clone(),
+        planet05: backdrop.clone(),
+        planet06: backdrop.clone(),
+        planet07: backdrop.clone(),
+        planet08: backdrop.clone(),
+        planet09: backdrop,
+        explosion: atlas.clone(),
+        weapons: world.resource_mut::<Assets<WeaponCatalog>>().add(weapons),
+        enemies: world.resource_mut::<Assets<EnemyArchetypes>>().add(enemies),
+        waves: world.resource_mut::<Assets<WaveScript>>().add(waves),
+        hit_red: atlas.clone(),
+        hit_blue: atlas.clone(),
+        hit_green: atlas,
+    }
+}
@@ -3,8 +3,12 @@ use std::time::Duration;
 use bevy::prelude::*;
 
 use crate::{
-    shooting::WeaponSwitchedEvent, AppState, Game, LevelUpEvent, Player, EARTH_HEALTH,
-    PLAYER_HEALTH,
+    boss::{Boss, BossDefeated, BossFight},
+    formation::SquadWipedEvent,
+    lives::PLAYER_LIVES,
+    rng::GameRng,
+    shooting::WeaponSwitchedEvent,
+    team_score, AppState, Enemy, Game, LevelUpEvent, Player, EARTH_HEALTH, PLAYER_HEALTH,
 };
 
 pub struct UiOverlayPlugin;
@@ -16,13 +20,27 @@ impl Plugin for UiOverlayPlugin {
             .add_system(update_stats.in_set(OnUpdate(AppState::InGame)))
             .add_system(level_up_msg.in_set(OnUpdate(AppState::InGame)))
             .add_system(weapon_switched_msg.in_set(OnUpdate(AppState::InGame)))
+            .add_system(update_boss_health_bar.in_set(OnUpdate(AppState::InGame)))
+            .add_system(boss_defeated_msg.in_set(OnUpdate(AppState::InGame)))
+            .add_system(squad_wiped_msg.in_set(OnUpdate(AppState::InGame)))
             .add_system(gameover_screen.in_schedule(OnEnter(AppState::GameOver)))
             .add_system(gamewon_screen.in_schedule(OnEnter(AppState::GameWon)))
             .add_system(pause_screen.in_schedule(OnEnter(AppState::Paused)))
-            .add_system(clear_msg_now.in_schedule(OnExit(AppState::Paused)));
+            .add_system(clear_msg_now.in_schedule(OnExit(AppState::Paused)))
+            .add_system(intro_msg.in_schedule(OnExit(AppState::GameOver)))
+            .add_system(intro_msg.in_schedule(OnExit(AppState::GameWon)))
+            .add_system(hide_hud.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(show_hud.in_schedule(OnExit(AppState::MainMenu)))
+            .add_system(intro_msg.in_schedule(OnExit(AppState::MainMenu)));
     }
 }
 
+const RESTART_HINT: &str = "\nPRESS R TO RESTART OR ESC FOR THE MENU";
+
+/// Root nodes of the in-game overlay, hidden while the menu is shown.
+#[derive(Component)]
+struct Hud;
+
 #[derive(Component)]
 struct HealthText;
 
@@ -35,6 +53,16 @@ struct ScoreText;
 #[derive(Component)]
 pub struct MessageText;
 
+/// Only shown during a boss fight.
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossNameText;
+
+#[derive(Component)]
+struct BossHealthFill;
+
 fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     let text_style = TextStyle {
         font: asset_server.load("fonts/impact.ttf"),
@@ -56,20 +84,24 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         }),
         ScoreText,
+        Hud,
     ));
 
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::width(Val::Percent(100.0)),
-                margin: UiRect::all(Val::Px(6.0)),
-                flex_direction: FlexDirection::Column,
-                justify_content: JustifyContent::SpaceBetween,
-                align_items: AlignItems::Center,
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::width(Val::Percent(100.0)),
+                    margin: UiRect::all(Val::Px(6.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            Hud,
+        ))
         .with_children(|parent| {
             parent.spawn((
                 TextBundle::from_sections([
@@ -92,15 +124,74 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             ));
 
             parent.spawn((
-                TextBundle::from_sections([
-                    TextSection::new("Health: ", text_style.clone()),
-                    TextSection::new(PLAYER_HEALTH.to_string(), text_style.clone()),
-                ])
+                TextBundle::from_sections([TextSection::new(
+                    format!("Health: {PLAYER_HEALTH}   Lives: {PLAYER_LIVES}"),
+                    text_style.clone(),
+                )])
                 .with_text_alignment(TextAlignment::Center),
                 HealthText,
             ));
         });
 
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect::top(Val::Px(60.0)),
+                    size: Size::width(Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::width(Val::Percent(60.0)),
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    BossHealthBar,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("", text_style.clone()),
+                        BossNameText,
+                    ));
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.0), Val::Px(16.0)),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.25, 0.0, 0.0).into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        size: Size::height(Val::Percent(100.0)),
+                                        ..default()
+                                    },
+                                    background_color: Color::rgb(0.85, 0.1, 0.1).into(),
+                                    ..default()
+                                },
+                                BossHealthFill,
+                            ));
+                        });
+                });
+        });
+
     commands.insert_resource(MessageConfig {
         msg_timer: Timer::new(Duration::from_secs_f32(4.25), TimerMode::Once),
     })
@@ -130,10 +221,95 @@ fn update_stats(
         Query<&mut Text, With<ScoreText>>,
     )>,
     game: Res<Game>,
+    player_query: Query<&Player>,
 ) {
-    set.p0().single_mut().sections[1].value = format!("{}", game.health);
+    let mut players: Vec<_> = player_query.iter().collect();
+    players.sort_by_key(|player| player.index);
+
+    // In co-op every player gets their own stats, the score at the top is the one of the team.
+    let stats = match players[..] {
+        [player] => format!("Health: {}   Lives: {}", player.health, player.lives),
+        _ => players
+            .iter()
+            .map(|player| {
+                format!(
+                    "P{} Health: {}   Lives: {}   Score: {}",
+                    player.index + 1,
+                    player.health,
+                    player.lives,
+                    player.score
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("      "),
+    };
+
+    set.p0().single_mut().sections[0].value = stats;
     set.p1().single_mut().sections[1].value = format!("{}", game.earth_health);
-    set.p2().single_mut().sections[1].value = format!("{}", game.score);
+    set.p2().single_mut().sections[1].value = format!("{}", team_score(players.iter().copied()));
+}
+
+fn update_boss_health_bar(
+    mut bar_query: Query<&mut Visibility, With<BossHealthBar>>,
+    mut name_query: Query<&mut Text, With<BossNameText>>,
+    mut fill_query: Query<&mut Style, With<BossHealthFill>>,
+    fight: Option<Res<BossFight>>,
+    boss_query: Query<(&Boss, &Enemy)>,
+) {
+    let boss = fight.and_then(|fight| boss_query.get(fight.boss).ok());
+
+    *bar_query.single_mut() = match boss {
+        Some(_) => Visibility::Inherited,
+        None => Visibility::Hidden,
+    };
+    if let Some((boss, enemy)) = boss {
+        name_query.single_mut().sections[0].value = boss.name.clone();
+        fill_query.single_mut().size.width =
+            Val::Percent(100.0 * enemy.health as f32 / boss.full_health as f32);
+    }
+}
+
+fn boss_defeated_msg(
+    mut query: Query<&mut Text, With<MessageText>>,
+    mut config: ResMut<MessageConfig>,
+    mut ev_bossdefeated: EventReader<BossDefeated>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in ev_bossdefeated.iter() {
+        let text_style = TextStyle {
+            font: asset_server.load("fonts/impact.ttf"),
+            font_size: 42.0,
+            color: Color::WHITE,
+        };
+
+        let mut text = query.single_mut();
+        text.sections = vec![TextSection::new("BOSS DEFEATED!", text_style)];
+
+        config.msg_timer = Timer::new(Duration::from_secs_f32(3.0), TimerMode::Once);
+    }
+}
+
+fn squad_wiped_msg(
+    mut query: Query<&mut Text, With<MessageText>>,
+    mut config: ResMut<MessageConfig>,
+    mut ev_squadwiped: EventReader<SquadWipedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    for ev in ev_squadwiped.iter() {
+        let text_style = TextStyle {
+            font: asset_server.load("fonts/impact.ttf"),
+            font_size: 42.0,
+            color: Color::WHITE,
+        };
+
+        let mut text = query.single_mut();
+        text.sections = vec![
+            TextSection::new("SQUADRON WIPED OUT! +", text_style.clone()),
+            TextSection::new(ev.bonus.to_string(), text_style.clone()),
+        ];
+
+        config.msg_timer = Timer::new(Duration::from_secs_f32(1.5), TimerMode::Once);
+    }
 }
 
 fn level_up_msg(
@@ -141,9 +317,8 @@ fn level_up_msg(
     mut config: ResMut<MessageConfig>,
     mut ev_levelup: EventReader<LevelUpEvent>,
     asset_server: Res<AssetServer>,
-    game: Res<Game>,
 ) {
-    for _ev in ev_levelup.iter() {
+    for ev in ev_levelup.iter() {
         // TODO: Text seems to render differently than original despite using the same font (double check) and same font size.
         let text_style = TextStyle {
             font: asset_server.load("fonts/impact.ttf"),
@@ -154,7 +329,7 @@ fn level_up_msg(
         let mut text = query.single_mut();
         text.sections = vec![
             TextSection::new("LEVEL UP! New weapon in slot ", text_style.clone()),
-            TextSection::new(game.level.to_string(), text_style.clone()),
+            TextSection::new(ev.level.to_string(), text_style.clone()),
             TextSection::new(" unlocked.", text_style.clone()),
         ];
 
@@ -167,11 +342,13 @@ fn weapon_switched_msg(
     mut config: ResMut<MessageConfig>,
     mut ev_weaponswitched: EventReader<WeaponSwitchedEvent>,
     asset_server: Res<AssetServer>,
-    mut player_query: Query<(&mut Player, &mut Transform, &mut Handle<Image>)>,
+    mut player_query: Query<&mut Player>,
 ) {
-    let (mut player, _pos, _img) = player_query.single_mut();
+    for ev in ev_weaponswitched.iter() {
+        let Ok(mut player) = player_query.get_mut(ev.0) else {
+            continue;
+        };
 
-    for _ev in ev_weaponswitched.iter() {
         // TODO: Text seems to render differently than original despite using the same font (double check) and same font size.
         let text_style = TextStyle {
             font: asset_server.load("fonts/impact.ttf"),
@@ -192,7 +369,8 @@ fn weapon_switched_msg(
 fn gameover_screen(
     mut query: Query<&mut Text, With<MessageText>>,
     asset_server: Res<AssetServer>,
-    game: Res<Game>,
+    player_query: Query<&Player>,
+    rng: Res<GameRng>,
 ) {
     // TODO: Text seems to render differently than original despite using the same font (double check) and same font size.
     let text_style = TextStyle {
@@ -205,7 +383,10 @@ fn gameover_screen(
     text.sections = vec![
         TextSection::new("GAME OVER!\n", text_style.clone()),
         TextSection::new("YOUR SCORE: ", text_style.clone()),
-        TextSection::new(game.score.to_string(), text_style.clone()),
+        TextSection::new(team_score(&player_query).to_string(), text_style.clone()),
+        TextSection::new("\nSEED: ", text_style.clone()),
+        TextSection::new(rng.seed.to_string(), text_style.clone()),
+        TextSection::new(RESTART_HINT, text_style.clone()),
     ];
 }
 
@@ -213,6 +394,8 @@ fn gamewon_screen(
     mut query: Query<&mut Text, With<MessageText>>,
     asset_server: Res<AssetServer>,
     game: Res<Game>,
+    player_query: Query<&Player>,
+    rng: Res<GameRng>,
 ) {
     // TODO: Text seems to render differently than original despite using the same font (double check) and same font size.
     let text_style = TextStyle {
@@ -228,16 +411,52 @@ fn gamewon_screen(
             text_style.clone(),
         ),
         TextSection::new("YOUR SCORE: ", text_style.clone()),
-        TextSection::new(game.score.to_string(), text_style.clone()),
+        TextSection::new(team_score(&player_query).to_string(), text_style.clone()),
         TextSection::new("\n", text_style.clone()),
         TextSection::new("YOUR FINAL SCORE: ", text_style.clone()),
         TextSection::new(
-            (game.score + game.earth_health + game.health).to_string(),
+            game.final_score(&player_query).to_string(),
             text_style.clone(),
         ),
+        TextSection::new("\nSEED: ", text_style.clone()),
+        TextSection::new(rng.seed.to_string(), text_style.clone()),
+        TextSection::new(RESTART_HINT, text_style.clone()),
     ];
 }
 
+/// Shown at the start of every game.
+fn intro_msg(
+    mut query: Query<&mut Text, With<MessageText>>,
+    mut config: ResMut<MessageConfig>,
+    asset_server: Res<AssetServer>,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/impact.ttf"),
+        font_size: 39.0,
+        color: Color::WHITE,
+    };
+
+    let mut text = query.single_mut();
+    text.sections = vec![TextSection::new(
+        "PROTECT EARTH AS LONG AS YOU CAN!!!",
+        text_style,
+    )];
+
+    config.msg_timer = Timer::new(Duration::from_secs_f32(4.25), TimerMode::Once);
+}
+
+fn hide_hud(mut query: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in &mut query {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn show_hud(mut query: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in &mut query {
+        *visibility = Visibility::Inherited;
+    }
+}
+
 fn pause_screen(mut query: Query<&mut Text, With<MessageText>>, asset_server: Res<AssetServer>) {
     // TODO: Text seems to render differently than original despite using the same font (double check) and same font size.
     let text_style = TextStyle {